@@ -0,0 +1,146 @@
+use crate::{scene, three};
+use std::*;
+
+// Error for command line parsing failures.
+#[derive(Debug)]
+pub struct ArgsError {
+    msg: String
+}
+
+impl ArgsError {
+    fn new(msg: &str) -> ArgsError { ArgsError { msg: msg.to_string() } }
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl error::Error for ArgsError {}
+
+// What the invocation asks t3d to do.
+#[derive(PartialEq)]
+pub enum Mode {
+    Help,
+    Version,
+    View,
+
+    // Time rendering a grid of copies of the model, without a terminal.
+    Benchmark
+}
+
+// Parsed command line arguments.
+pub struct Args {
+    pub mode: Mode,
+    pub file_paths: Vec<String>,
+
+    // Axis that points up in the files, models are turned so that it points up (y+) on screen.
+    pub up: three::Axis,
+
+    // How to place models when viewing more than one.
+    pub layout: scene::Layout,
+
+    // Whether to show the difference between two files, instead of the files themselves.
+    pub diff: bool,
+
+    // Distance under which diffed vertices are considered the same, defaults to relative to the models' size.
+    pub tolerance: Option<f32>,
+
+    // Whether to tune output for slow links, such as SSH over a VPN.
+    pub remote: bool,
+
+    // Number of threads to render with, defaults to one per core.
+    pub threads: Option<usize>,
+
+    // File of keyframes to fly the camera through.
+    pub keyframes: Option<String>
+}
+
+impl Args {
+    // Parse the arguments, excluding the binary name.
+    pub fn parse(args: &[String]) -> Result<Args, ArgsError> {
+        let mut parsed = Args {
+            mode: Mode::View,
+            file_paths: Vec::new(),
+            up: three::Axis::Y,
+            layout: scene::Layout::Grid,
+            diff: false,
+            tolerance: None,
+            remote: false,
+            threads: None,
+            keyframes: None
+        };
+
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            // Support both "--flag value" and "--flag=value".
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with('-') => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None)
+            };
+
+            // Fetch the value of a flag that requires one.
+            let mut value = |name: &str| match inline_value.clone().or_else(|| args.next().cloned()) {
+                Some(value) => Ok(value),
+                None => Err(ArgsError::new(&format!("Missing value for {}.", name)))
+            };
+
+            match flag {
+                "-h" | "-help" | "--h" | "--help" => parsed.mode = Mode::Help,
+                "-v" | "-version" | "--v" | "--version" => {
+                    if parsed.mode != Mode::Help { parsed.mode = Mode::Version }
+                }
+                "--up" => {
+                    parsed.up = match value("--up")?.to_lowercase().as_str() {
+                        "x" => three::Axis::X,
+                        "y" => three::Axis::Y,
+                        "z" => three::Axis::Z,
+                        _ => return Err(ArgsError::new("--up must be one of x, y or z."))
+                    }
+                }
+                "--layout" => {
+                    parsed.layout = match value("--layout")?.to_lowercase().as_str() {
+                        "grid" => scene::Layout::Grid,
+                        "overlay" => scene::Layout::Overlay,
+                        _ => return Err(ArgsError::new("--layout must be one of grid or overlay."))
+                    }
+                }
+                "--benchmark" => {
+                    if parsed.mode == Mode::View { parsed.mode = Mode::Benchmark }
+                }
+                "--diff" => parsed.diff = true,
+                "--remote" => parsed.remote = true,
+                "--threads" => {
+                    parsed.threads = match value("--threads")?.parse::<usize>() {
+                        Ok(threads) if threads > 0 => Some(threads),
+                        _ => return Err(ArgsError::new("--threads must be a positive whole number."))
+                    }
+                }
+                "--keyframes" => parsed.keyframes = Some(value("--keyframes")?),
+                "--tolerance" => {
+                    parsed.tolerance = match value("--tolerance")?.parse::<f32>() {
+                        Ok(tolerance) if tolerance >= 0. => Some(tolerance),
+                        _ => return Err(ArgsError::new("--tolerance must be a non-negative number."))
+                    }
+                }
+                flag if flag.starts_with('-') => {
+                    return Err(ArgsError::new(&format!("Unknown option {}.", flag)))
+                }
+                _ => parsed.file_paths.push(arg.clone())
+            }
+        }
+
+        // Running with no files shows help.
+        let needs_files = parsed.mode == Mode::View || parsed.mode == Mode::Benchmark;
+        if needs_files && parsed.file_paths.is_empty() { parsed.mode = Mode::Help }
+
+        let runs = parsed.mode == Mode::View || parsed.mode == Mode::Benchmark;
+        if runs && parsed.diff && parsed.file_paths.len() != 2 {
+            return Err(ArgsError::new("--diff compares exactly two files, the old one and then the new one."))
+        }
+
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,43 @@
+use crate::{scene, screen, three};
+use std::*;
+use time::Instant;
+
+// Number of frames to time for every thread count.
+const FRAMES: u32 = 30;
+
+// Resolution to render at, in subpixels.
+const WIDTH: u16 = 400;
+const HEIGHT: u16 = 200;
+
+// Times rendering the scene's edges off screen, with one thread and then with every thread.
+// Returns a report of the time per frame.
+pub fn run(scene: &scene::Scene, camera: &mut three::Camera) -> String {
+    camera.screen.resize(WIDTH, HEIGHT);
+    let edge_count: usize = scene.models.iter().map(|model| model.edges.len()).sum();
+    let mut report = format!("{} edges at {} x {}:\n", edge_count, WIDTH, HEIGHT);
+
+    let all_threads = camera.threads;
+    let mut thread_counts = vec![1, all_threads];
+    thread_counts.dedup();
+
+    for threads in thread_counts {
+        camera.threads = threads;
+
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            camera.screen.clear();
+            for model in scene.models.iter() {
+                camera.plot_model_edges(model);
+            }
+
+            // Include producing the glyphs, but not writing them anywhere.
+            camera.screen.render::<screen::BrailePixel>(&mut io::sink()).unwrap();
+        }
+        let per_frame = start.elapsed() / FRAMES;
+
+        report += &format!("    {} thread(s): {:.2} ms per frame\n", threads, per_frame.as_secs_f32() * 1000.);
+    }
+
+    camera.threads = all_threads;
+    report
+}
@@ -0,0 +1,179 @@
+use crate::{three, view};
+use std::*;
+use collections::BTreeMap;
+
+// First lines of a bookmarks file, describing its columns.
+const HEADER: &str = "\
+# t3d camera bookmarks, one per line: slot, center x y z in the file's coordinates, yaw, pitch, roll, distance,
+# field of view, orthographic width (0 for perspective), and whether vertices, braille and color modes are on (1)
+# or off (0).
+";
+
+// Error for bookmarks file parsing failures.
+#[derive(Debug)]
+struct BookmarksParseError {
+    line: usize
+}
+
+impl BookmarksParseError {
+    fn new(line: usize) -> BookmarksParseError { BookmarksParseError { line } }
+}
+
+impl fmt::Display for BookmarksParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error parsing line {}.", self.line)
+    }
+}
+
+impl error::Error for BookmarksParseError {}
+
+// A saved camera, and how the scene was drawn.
+// Where it is kept, the center is in the coordinates of the file, as the caller converts it.
+#[derive(Copy, Clone)]
+pub struct Bookmark {
+    pub view: view::View,
+    pub fov: f32,
+
+    // Width of the view when orthographic, or None for perspective.
+    pub orthographic_width: Option<f32>,
+
+    pub points_mode: bool,
+    pub braille_mode: bool,
+    pub color_mode: bool
+}
+
+// Bookmarks in numbered slots, kept in a file next to a model.
+pub struct Bookmarks {
+    path: path::PathBuf,
+    slots: BTreeMap<u32, Bookmark>
+}
+
+impl Bookmarks {
+    // Create empty bookmarks for a model file, kept at its path with .t3d appended.
+    pub fn new(model_path: &str) -> Bookmarks {
+        Bookmarks { path: path::PathBuf::from(format!("{}.t3d", model_path)), slots: BTreeMap::new() }
+    }
+
+    // Read the bookmarks from their file, if it exists.
+    pub fn load(&mut self) -> Result<(), Box<dyn error::Error>> {
+        let code = match fs::read_to_string(&self.path) {
+            Ok(code) => code,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(Box::from(error))
+        };
+        self.slots = Bookmarks::parse(&code)?;
+        Ok(())
+    }
+
+    // Read the bookmarks in the code of a bookmarks file, by slot.
+    fn parse(code: &str) -> Result<BTreeMap<u32, Bookmark>, Box<dyn error::Error>> {
+        let mut slots = BTreeMap::new();
+        for (index, line) in code.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+
+            let values: Result<Vec<f32>, _> = line.split_whitespace().map(str::parse::<f32>).collect();
+            let Ok(&[slot, x, y, z, yaw, pitch, roll, distance, fov, width, points, braille, color]) = values.as_deref() else {
+                return Err(Box::from(BookmarksParseError::new(index + 1)))
+            };
+            if slot.fract() != 0. || !(1. ..=9.).contains(&slot) {
+                return Err(Box::from(BookmarksParseError::new(index + 1)))
+            }
+
+            // Keep the camera within what the viewer can show.
+            if !(crate::MIN_FOV..=crate::MAX_FOV).contains(&fov) || !distance.is_finite() || distance <= 0. {
+                return Err(Box::from(BookmarksParseError::new(index + 1)))
+            }
+
+            slots.insert(slot as u32, Bookmark {
+                view: view::View { center: three::Point::new(x, y, z), yaw, pitch, roll, distance },
+                fov,
+                orthographic_width: if width > 0. { Some(width) } else { None },
+                points_mode: points != 0.,
+                braille_mode: braille != 0.,
+                color_mode: color != 0.
+            });
+        }
+        Ok(slots)
+    }
+
+    // Bookmark in a slot, if one was saved there.
+    pub fn get(&self, slot: u32) -> Option<&Bookmark> {
+        self.slots.get(&slot)
+    }
+
+    // Store a bookmark in a slot, replacing any already there, and write them all to their file.
+    pub fn set(&mut self, slot: u32, bookmark: Bookmark) -> io::Result<()> {
+        self.slots.insert(slot, bookmark);
+
+        let mut code = HEADER.to_string();
+        for (slot, bookmark) in self.slots.iter() {
+            let view = &bookmark.view;
+            code += &format!(
+                "{} {} {} {} {} {} {} {} {} {} {} {} {}\n",
+                slot, view.center.x, view.center.y, view.center.z, view.yaw, view.pitch, view.roll, view.distance,
+                bookmark.fov, bookmark.orthographic_width.unwrap_or(0.),
+                bookmark.points_mode as u8, bookmark.braille_mode as u8, bookmark.color_mode as u8
+            );
+        }
+        fs::write(&self.path, code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_nothing_without_a_file() {
+        let model_path = env::temp_dir().join(format!("t3d-bookmarks-missing-{}.obj", process::id()));
+        let mut bookmarks = Bookmarks::new(model_path.to_str().unwrap());
+        assert!(bookmarks.load().is_ok());
+        assert!((1..=9).all(|slot| bookmarks.get(slot).is_none()));
+    }
+
+    #[test]
+    fn saves_and_loads_bookmarks() {
+        let model_path = env::temp_dir().join(format!("t3d-bookmarks-{}.obj", process::id()));
+        let mut saved = Bookmarks::new(model_path.to_str().unwrap());
+        let view = view::View { center: three::Point::new(1., 2., 3.), yaw: 0.5, pitch: -0.25, roll: 0.125, distance: 7. };
+        saved.set(3, Bookmark {
+            view, fov: 1.5, orthographic_width: Some(4.), points_mode: true, braille_mode: false, color_mode: true
+        }).unwrap();
+
+        let mut loaded = Bookmarks::new(model_path.to_str().unwrap());
+        let result = loaded.load();
+        fs::remove_file(&saved.path).unwrap();
+        result.unwrap();
+
+        let bookmark = loaded.get(3).unwrap();
+        assert!(bookmark.view.center.x == 1. && bookmark.view.center.y == 2. && bookmark.view.center.z == 3.);
+        assert!(bookmark.view.yaw == 0.5 && bookmark.view.pitch == -0.25 && bookmark.view.roll == 0.125);
+        assert!(bookmark.view.distance == 7. && bookmark.fov == 1.5 && bookmark.orthographic_width == Some(4.));
+        assert!(bookmark.points_mode && !bookmark.braille_mode && bookmark.color_mode);
+        assert!(loaded.get(1).is_none());
+    }
+
+    #[test]
+    fn parses_perspective_bookmarks() {
+        let slots = Bookmarks::parse("# header\n\n9 0 0 0 0 0 0 5 1.7 0 0 1 1\n").unwrap();
+        assert_eq!(slots.keys().collect::<Vec<_>>(), vec![&9]);
+        assert!(slots[&9].orthographic_width.is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for (code, line) in [
+            ("# header\n1 0 0 0 0 0 0 5 1.7 0 0 1 1\n2 0 0 0 0 0 0 5 1.7 0 0 1\n", 3),
+            ("1 0 0 0 zero 0 0 5 1.7 0 0 1 1\n", 1),
+            ("10 0 0 0 0 0 0 5 1.7 0 0 1 1\n", 1),
+            ("1.5 0 0 0 0 0 0 5 1.7 0 0 1 1\n", 1),
+            ("1 0 0 0 0 0 0 5 0 0 0 1 1\n", 1),
+            ("1 0 0 0 0 0 0 -5 1.7 0 0 1 1\n", 1),
+            ("1 0 0 0 0 0 0 NaN 1.7 0 0 1 1\n", 1)
+        ] {
+            let error = Bookmarks::parse(code).err().unwrap();
+            assert_eq!(error.to_string(), format!("Error parsing line {}.", line));
+        }
+    }
+}
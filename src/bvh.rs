@@ -0,0 +1,268 @@
+use crate::three::Point;
+use std::*;
+use ops::Range;
+
+// Most edges a node holds before it is split in two.
+const MAX_LEAF_EDGES: usize = 32;
+
+// Box around a contiguous range of edges, and the two nodes splitting it, if any.
+#[derive(Clone)]
+struct Node {
+    min: Point,
+    max: Point,
+    edges: Range<usize>,
+    children: Option<(usize, usize)>
+}
+
+// Bounding volume hierarchy over the edges of a model, in model space.
+// Building it reorders the edges, so that every node covers a contiguous range of them.
+#[derive(Clone, Default)]
+pub struct Bvh {
+    // Root first, empty if not built.
+    nodes: Vec<Node>
+}
+
+// Min and max bounds of some edges.
+fn edge_bounds(edges: &[(Point, Point)]) -> (Point, Point) {
+    let mut min = Point::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    let mut max = Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for point in edges.iter().flat_map(|edge| [edge.0, edge.1]) {
+        min = Point::new(min.x.min(point.x), min.y.min(point.y), min.z.min(point.z));
+        max = Point::new(max.x.max(point.x), max.y.max(point.y), max.z.max(point.z));
+    }
+    (min, max)
+}
+
+// Component of a point along an axis, 0 for x, 1 for y and 2 for z.
+fn component(point: &Point, axis: usize) -> f32 {
+    match axis {
+        0 => point.x,
+        1 => point.y,
+        _ => point.z
+    }
+}
+
+fn sub(a: &Point, b: &Point) -> Point { Point::new(a.x - b.x, a.y - b.y, a.z - b.z) }
+fn dot(a: &Point, b: &Point) -> f32 { a.x * b.x + a.y * b.y + a.z * b.z }
+
+// Closest approach of a ray and an edge, as the distance along the ray,
+// the distance between them, and the closest point of the edge.
+// See https://en.wikipedia.org/wiki/Skew_lines#Nearest_points.
+fn closest_approach(origin: &Point, direction: &Point, edge: &(Point, Point)) -> (f32, f32, Point) {
+    let along = sub(&edge.1, &edge.0);
+    let offset = sub(&edge.0, origin);
+    let (a, b, c) = (dot(direction, direction), dot(direction, &along), dot(&along, &along));
+    let (d, e) = (dot(direction, &offset), dot(&along, &offset));
+
+    // Position along the edge, clamped to its ends, falling back to its start when parallel.
+    let denominator = a * c - b * b;
+    let s = if denominator > f32::EPSILON * a * c { ((b * d - a * e) / denominator).clamp(0., 1.) } else { 0. };
+
+    let on_edge = Point::new(edge.0.x + along.x * s, edge.0.y + along.y * s, edge.0.z + along.z * s);
+    let t = dot(&sub(&on_edge, origin), direction) / a;
+    let on_ray = Point::new(origin.x + direction.x * t, origin.y + direction.y * t, origin.z + direction.z * t);
+    let distance = dot(&sub(&on_edge, &on_ray), &sub(&on_edge, &on_ray)).sqrt();
+
+    (t, distance, on_edge)
+}
+
+// Range of distances along a ray at which it is inside a box, if it hits it.
+// See https://en.wikipedia.org/wiki/Slab_method.
+fn ray_box(origin: &Point, direction: &Point, min: &Point, max: &Point) -> Option<(f32, f32)> {
+    let (mut near, mut far) = (0f32, f32::INFINITY);
+    for axis in 0..3 {
+        let (origin, direction) = (component(origin, axis), component(direction, axis));
+        let (min, max) = (component(min, axis), component(max, axis));
+        if direction == 0. {
+            if origin < min || origin > max { return None }
+            continue
+        }
+
+        let (a, b) = ((min - origin) / direction, (max - origin) / direction);
+        near = near.max(a.min(b));
+        far = far.min(a.max(b));
+        if near > far { return None }
+    }
+    Some((near, far))
+}
+
+impl Bvh {
+    // An empty hierarchy, for models that are not worth building one for.
+    pub fn new() -> Bvh {
+        Bvh { nodes: Vec::new() }
+    }
+
+    // Build a hierarchy over the edges, splitting nodes in half along their longest side.
+    pub fn build(edges: &mut [(Point, Point)]) -> Bvh {
+        let mut nodes = Vec::new();
+        if edges.is_empty() { return Bvh { nodes } }
+
+        let (min, max) = edge_bounds(edges);
+        nodes.push(Node { min, max, edges: 0..edges.len(), children: None });
+
+        let mut pending = vec![0];
+        while let Some(index) = pending.pop() {
+            let range = nodes[index].edges.clone();
+            if range.len() <= MAX_LEAF_EDGES { continue }
+
+            // Split at the median edge midpoint along the longest side of the node.
+            let (min, max) = (nodes[index].min, nodes[index].max);
+            let size = sub(&max, &min);
+            let axis = if size.x >= size.y && size.x >= size.z { 0 } else if size.y >= size.z { 1 } else { 2 };
+            let middle = range.len() / 2;
+            edges[range.clone()].select_nth_unstable_by(middle, |a, b| {
+                let a = component(&a.0, axis) + component(&a.1, axis);
+                let b = component(&b.0, axis) + component(&b.1, axis);
+                a.total_cmp(&b)
+            });
+
+            let mut children = (0, 0);
+            for (child, child_range) in [
+                (&mut children.0, range.start..range.start + middle),
+                (&mut children.1, range.start + middle..range.end)
+            ] {
+                let (min, max) = edge_bounds(&edges[child_range.clone()]);
+                *child = nodes.len();
+                pending.push(nodes.len());
+                nodes.push(Node { min, max, edges: child_range, children: None });
+            }
+            nodes[index].children = Some(children);
+        }
+
+        Bvh { nodes }
+    }
+
+    // Whether the hierarchy has not been built.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Ranges of edges in boxes that may be visible, given a test of a box's (min, max) bounds.
+    // Whole subtrees are skipped when their box is not visible, and neighbouring ranges are joined.
+    pub fn visible(&self, is_visible: impl Fn(&Point, &Point) -> bool) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        let mut pending = if self.nodes.is_empty() { Vec::new() } else { vec![0] };
+        while let Some(index) = pending.pop() {
+            let node = &self.nodes[index];
+            if !is_visible(&node.min, &node.max) { continue }
+
+            match node.children {
+                // Visit the first child first, so that ranges come out in order.
+                Some((first, second)) => pending.extend([second, first]),
+                None => match ranges.last_mut() {
+                    Some(last) if last.end == node.edges.start => last.end = node.edges.end,
+                    _ => ranges.push(node.edges.clone())
+                }
+            }
+        }
+        ranges
+    }
+
+    // Find the point of the given edges nearest to the origin of a ray,
+    // that is within tolerance times its distance along the ray from it.
+    // The edges must be those the hierarchy was built over.
+    pub fn pick(&self, edges: &[(Point, Point)], origin: &Point, direction: &Point, tolerance: f32) -> Option<Point> {
+        let mut best: Option<(f32, Point)> = None;
+        let mut pending = if self.nodes.is_empty() { Vec::new() } else { vec![0] };
+        while let Some(index) = pending.pop() {
+            let node = &self.nodes[index];
+
+            // Grow the box by the tolerance at its far corner, so it can't miss a nearby edge.
+            let far_corner = Point::new(
+                (node.min.x - origin.x).abs().max((node.max.x - origin.x).abs()),
+                (node.min.y - origin.y).abs().max((node.max.y - origin.y).abs()),
+                (node.min.z - origin.z).abs().max((node.max.z - origin.z).abs())
+            );
+            let margin = tolerance * dot(&far_corner, &far_corner).sqrt();
+            let min = Point::new(node.min.x - margin, node.min.y - margin, node.min.z - margin);
+            let max = Point::new(node.max.x + margin, node.max.y + margin, node.max.z + margin);
+
+            let Some((near, _)) = ray_box(origin, direction, &min, &max) else { continue };
+            if best.is_some_and(|(best, _)| near > best) { continue }
+
+            match node.children {
+                Some((first, second)) => pending.extend([first, second]),
+                None => for edge in edges[node.edges.clone()].iter() {
+                    let (t, distance, point) = closest_approach(origin, direction, edge);
+                    let within = t > 0. && distance <= tolerance * t * dot(direction, direction).sqrt();
+                    if within && best.is_none_or(|(best, _)| t < best) { best = Some((t, point)) }
+                }
+            }
+        }
+        best.map(|(_, point)| point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Edges along z, at x from 0 to count - 1, so that the hierarchy has to split them.
+    fn rungs(count: usize) -> Vec<(Point, Point)> {
+        (0..count).map(|x| (Point::new(x as f32, 0., -1.), Point::new(x as f32, 0., 1.))).collect()
+    }
+
+    #[test]
+    fn closest_approach_of_crossing_lines() {
+        // Ray down the x axis, passing 2 above the middle of an edge along z.
+        let edge = (Point::new(5., 2., -1.), Point::new(5., 2., 1.));
+        let (t, distance, point) = closest_approach(&Point::new(0., 0., 0.), &Point::new(1., 0., 0.), &edge);
+        assert!((t - 5.).abs() < 1e-5);
+        assert!((distance - 2.).abs() < 1e-5);
+        assert!(point.x == 5. && point.y == 2. && point.z.abs() < 1e-5);
+    }
+
+    #[test]
+    fn closest_approach_clamps_to_the_edge() {
+        let edge = (Point::new(5., 0., 1.), Point::new(5., 0., 3.));
+        let (_, distance, point) = closest_approach(&Point::new(0., 0., 0.), &Point::new(1., 0., 0.), &edge);
+        assert!((distance - 1.).abs() < 1e-5);
+        assert!((point.z - 1.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn pick_hits_an_edge() {
+        let mut edges = rungs(100);
+        let bvh = Bvh::build(&mut edges);
+        let point = bvh.pick(&edges, &Point::new(40., 0.1, -10.), &Point::new(0., 0., 1.), 0.05).unwrap();
+        assert!(point.x == 40. && point.y == 0.);
+    }
+
+    #[test]
+    fn pick_misses_far_edges() {
+        let mut edges = rungs(100);
+        let bvh = Bvh::build(&mut edges);
+        assert!(bvh.pick(&edges, &Point::new(40., 5., -10.), &Point::new(0., 0., 1.), 0.05).is_none());
+        assert!(bvh.pick(&edges, &Point::new(40., 0., -10.), &Point::new(0., 0., -1.), 0.05).is_none());
+        assert!(Bvh::new().pick(&edges, &Point::new(40., 0., -10.), &Point::new(0., 0., 1.), 0.05).is_none());
+    }
+
+    #[test]
+    fn pick_finds_the_nearest_edge() {
+        // Looking along x, every rung is in the way, and the first one is nearest.
+        let mut edges = rungs(100);
+        let bvh = Bvh::build(&mut edges);
+        let point = bvh.pick(&edges, &Point::new(-10., 0., 0.), &Point::new(1., 0., 0.), 0.01).unwrap();
+        assert_eq!(point.x, 0.);
+
+        let point = bvh.pick(&edges, &Point::new(110., 0., 0.), &Point::new(-1., 0., 0.), 0.01).unwrap();
+        assert_eq!(point.x, 99.);
+    }
+
+    #[test]
+    fn visible_ranges_are_in_order() {
+        let mut edges = rungs(1000);
+        let bvh = Bvh::build(&mut edges);
+
+        // Everything visible joins into a single range.
+        assert_eq!(bvh.visible(|_, _| true), vec![0..1000]);
+        assert!(bvh.visible(|_, _| false).is_empty());
+
+        // Boxes overlapping the middle come out sorted, apart, and covering every edge in it.
+        let ranges = bvh.visible(|min, max| max.x >= 250. && min.x < 750.);
+        assert!(ranges.windows(2).all(|pair| pair[0].end < pair[1].start));
+        let covered: Vec<f32> = ranges.into_iter().flatten().map(|index| edges[index].0.x).collect();
+        assert!((250..750).all(|x| covered.contains(&(x as f32))));
+        assert!(covered.len() < 1000);
+    }
+}
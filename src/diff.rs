@@ -0,0 +1,202 @@
+use crate::{model, three};
+use std::*;
+use collections::HashMap;
+use crossterm::style;
+
+// Colors of the diff, by where the geometry exists.
+const REMOVED_COLOR: style::Color = style::Color::Red;
+const ADDED_COLOR: style::Color = style::Color::Green;
+const COMMON_COLOR: style::Color = style::Color::DarkGrey;
+
+// Hashes points into cubic cells as wide as the tolerance,
+// so that every point within the tolerance of another is in a neighbouring cell.
+struct PointGrid {
+    tolerance: f32,
+    cells: HashMap<(i64, i64, i64), Vec<usize>>
+}
+
+impl PointGrid {
+    fn new(tolerance: f32) -> PointGrid {
+        PointGrid { tolerance, cells: HashMap::new() }
+    }
+
+    fn cell(&self, point: &three::Point) -> (i64, i64, i64) {
+        (
+            (point.x / self.tolerance).floor() as i64,
+            (point.y / self.tolerance).floor() as i64,
+            (point.z / self.tolerance).floor() as i64
+        )
+    }
+
+    // Record a point, identified by the given index.
+    fn insert(&mut self, point: &three::Point, index: usize) {
+        self.cells.entry(self.cell(point)).or_default().push(index);
+    }
+
+    // Indices of every point inserted in the cell of the given point, or a neighbouring cell.
+    fn candidates(&self, point: &three::Point) -> impl Iterator<Item=usize> + '_ {
+        let (x, y, z) = self.cell(point);
+        (-1..=1).flat_map(move |dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (x + dx, y + dy, z + dz))))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+}
+
+// Whether two points are within the tolerance of each other.
+fn near(a: &three::Point, b: &three::Point, tolerance: f32) -> bool {
+    (a.x - b.x).powi(2) + (a.y - b.y).powi(2) + (a.z - b.z).powi(2) <= tolerance.powi(2)
+}
+
+// Vertices and edges of a model.
+type Geometry = (Vec<three::Point>, Vec<(three::Point, three::Point)>);
+
+// Vertices and edges of a model in world space.
+fn world_geometry(model: &model::Model) -> Geometry {
+    (
+        model.points.iter().map(|point| model.model_to_world(point)).collect(),
+        model.edges.iter()
+            .map(|edge| (model.model_to_world(&edge.0), model.model_to_world(&edge.1)))
+            .collect()
+    )
+}
+
+// Splits the vertices and edges of `from` into those that have a match in `to`, and those that don't.
+// Edges match in either direction.
+fn partition(from: &Geometry, to: &Geometry, tolerance: f32) -> (model::Model, model::Model) {
+    let mut matched = model::Model::new(Vec::new(), Vec::new(), three::Transform::identity());
+    let mut unmatched = model::Model::new(Vec::new(), Vec::new(), three::Transform::identity());
+
+    // Match vertices.
+    let mut vertex_grid = PointGrid::new(tolerance);
+    for (index, point) in to.0.iter().enumerate() {
+        vertex_grid.insert(point, index);
+    }
+
+    for point in from.0.iter() {
+        let found = vertex_grid.candidates(point).any(|index| near(point, &to.0[index], tolerance));
+        if found { matched.points.push(*point) } else { unmatched.points.push(*point) }
+    }
+
+    // Match edges, looking them up by either endpoint.
+    let mut edge_grid = PointGrid::new(tolerance);
+    for (index, edge) in to.1.iter().enumerate() {
+        edge_grid.insert(&edge.0, index);
+        edge_grid.insert(&edge.1, index);
+    }
+
+    for edge in from.1.iter() {
+        let found = edge_grid.candidates(&edge.0).any(|index| {
+            let other = &to.1[index];
+            (near(&edge.0, &other.0, tolerance) && near(&edge.1, &other.1, tolerance)) ||
+            (near(&edge.0, &other.1, tolerance) && near(&edge.1, &other.0, tolerance))
+        });
+        if found { matched.edges.push(*edge) } else { unmatched.edges.push(*edge) }
+    }
+
+    (matched, unmatched)
+}
+
+// Result of comparing two models.
+pub struct Diff {
+    // Geometry only in the old model.
+    pub removed: model::Model,
+
+    // Geometry only in the new model.
+    pub added: model::Model,
+
+    // Geometry in both, as found in the new model.
+    pub common: model::Model
+}
+
+impl Diff {
+    // Compares two models in world space, matching vertices and edges within the tolerance.
+    pub fn new(old: &model::Model, new: &model::Model, tolerance: f32) -> Diff {
+        // Guard against a zero tolerance, which would make every cell infinitely small.
+        let tolerance = tolerance.max(f32::EPSILON);
+        let (old, new) = (world_geometry(old), world_geometry(new));
+
+        let (_, mut removed) = partition(&old, &new, tolerance);
+        let (mut common, mut added) = partition(&new, &old, tolerance);
+
+        for model in [&mut removed, &mut added, &mut common] {
+            model.build_lods();
+            model.build_bvh();
+        }
+
+        removed.color = REMOVED_COLOR;
+        added.color = ADDED_COLOR;
+        common.color = COMMON_COLOR;
+
+        Diff { removed, added, common }
+    }
+
+    // Models to draw, unchanged geometry first so that changes are drawn over it.
+    pub fn into_models(self) -> Vec<model::Model> {
+        vec![self.common, self.removed, self.added]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32, z: f32) -> three::Point { three::Point::new(x, y, z) }
+
+    #[test]
+    fn matches_within_tolerance() {
+        let old = model::Model::new(
+            vec![point(0., 0., 0.), point(1., 0., 0.), point(0., 1., 0.)],
+            vec![(point(0., 0., 0.), point(1., 0., 0.)), (point(1., 0., 0.), point(0., 1., 0.))],
+            three::Transform::identity()
+        );
+        let new = model::Model::new(
+            vec![point(0.0005, 0., 0.), point(1., 0., 0.), point(5., 5., 5.)],
+            vec![(point(0.0005, 0., 0.), point(1., 0., 0.)), (point(0., 0., 0.), point(5., 5., 5.))],
+            three::Transform::identity()
+        );
+        let diff = Diff::new(&old, &new, 0.001);
+
+        assert_eq!((diff.common.points.len(), diff.common.edges.len()), (2, 1));
+        assert_eq!((diff.removed.points.len(), diff.removed.edges.len()), (1, 1));
+        assert_eq!((diff.added.points.len(), diff.added.edges.len()), (1, 1));
+        assert_eq!(diff.removed.points[0].y, 1.);
+        assert_eq!(diff.added.points[0].z, 5.);
+    }
+
+    #[test]
+    fn matches_reversed_edges() {
+        let edge = (point(0., 0., 0.), point(1., 2., 3.));
+        let old = model::Model::new(Vec::new(), vec![edge], three::Transform::identity());
+        let new = model::Model::new(Vec::new(), vec![(edge.1, edge.0)], three::Transform::identity());
+        let diff = Diff::new(&old, &new, 0.001);
+
+        assert_eq!(diff.common.edges.len(), 1);
+        assert!(diff.removed.edges.is_empty() && diff.added.edges.is_empty());
+    }
+
+    #[test]
+    fn compares_in_world_space() {
+        // The same edge, moved by its transform in one model, and in its points in the other.
+        let old = model::Model::new(
+            Vec::new(),
+            vec![(point(0., 0., 0.), point(1., 0., 0.))],
+            three::Transform::new(point(0., 2., 0.), three::Quaternion::identity(), point(1., 1., 1.))
+        );
+        let new = model::Model::new(Vec::new(), vec![(point(0., 2., 0.), point(1., 2., 0.))], three::Transform::identity());
+        let diff = Diff::new(&old, &new, 0.001);
+
+        assert_eq!(diff.common.edges.len(), 1);
+        assert!(diff.removed.edges.is_empty() && diff.added.edges.is_empty());
+    }
+
+    #[test]
+    fn differs_beyond_tolerance() {
+        let old = model::Model::new(vec![point(0., 0., 0.)], Vec::new(), three::Transform::identity());
+        let new = model::Model::new(vec![point(0.01, 0., 0.)], Vec::new(), three::Transform::identity());
+        let diff = Diff::new(&old, &new, 0.001);
+
+        assert!(diff.common.points.is_empty());
+        assert_eq!((diff.removed.points.len(), diff.added.points.len()), (1, 1));
+    }
+}
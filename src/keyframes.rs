@@ -0,0 +1,192 @@
+use crate::{three, view};
+use std::*;
+
+// Closest the camera gets to the center between keyframes, relative to the nearer of them,
+// as the curve can overshoot past it.
+const MIN_DISTANCE_FRACTION: f32 = 0.1;
+
+// Error for keyframes file parsing failures.
+#[derive(Debug)]
+struct KeyframesParseError {
+    msg: String
+}
+
+impl KeyframesParseError {
+    fn new(msg: &str) -> KeyframesParseError { KeyframesParseError { msg: msg.to_string() } }
+}
+
+impl fmt::Display for KeyframesParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl error::Error for KeyframesParseError {}
+
+// Where the camera is at a moment of a path.
+#[derive(Copy, Clone)]
+struct Keyframe {
+    // In seconds from the start.
+    time: f32,
+    view: view::View,
+    fov: f32
+}
+
+// Camera path through keyframes, sorted by time.
+pub struct Keyframes {
+    keyframes: Vec<Keyframe>
+}
+
+// Point at u, from 0 to 1, on the curve from b to c, that also passes through a before and d after.
+// See https://en.wikipedia.org/wiki/Cubic_Hermite_spline#Catmull%E2%80%93Rom_spline.
+fn catmull_rom(a: f32, b: f32, c: f32, d: f32, u: f32) -> f32 {
+    0.5 * (
+        2. * b +
+        (c - a) * u +
+        (2. * a - 5. * b + 4. * c - d) * u * u +
+        (3. * (b - c) + d - a) * u * u * u
+    )
+}
+
+impl Keyframes {
+    // Read keyframes from a file, one per line, as a time in seconds, yaw, pitch, distance, center x y z and field of view.
+    // Angles are in degrees, and the center is in the coordinates of the file being viewed, as the caller converts it.
+    // Empty lines and lines starting with # are skipped.
+    pub fn load(path: &str) -> Result<Keyframes, Box<dyn error::Error>> {
+        Keyframes::parse(&fs::read_to_string(path)?)
+    }
+
+    // Read keyframes from the code of a keyframes file.
+    fn parse(code: &str) -> Result<Keyframes, Box<dyn error::Error>> {
+        let mut keyframes = Vec::<Keyframe>::new();
+        for (index, line) in code.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+
+            let values: Result<Vec<f32>, _> = line.split_whitespace().map(str::parse::<f32>).collect();
+            let Ok(&[time, yaw, pitch, distance, x, y, z, fov]) = values.as_deref() else {
+                return Err(Box::from(KeyframesParseError::new(&format!(
+                    "Line {} must hold a time, yaw, pitch, distance, center x y z and field of view.", index + 1
+                ))))
+            };
+            if keyframes.last().is_some_and(|last| time <= last.time) {
+                return Err(Box::from(KeyframesParseError::new(&format!(
+                    "Line {} must come later than the one before.", index + 1
+                ))))
+            }
+            if !(crate::MIN_FOV..=crate::MAX_FOV).contains(&fov.to_radians()) {
+                return Err(Box::from(KeyframesParseError::new(&format!(
+                    "Line {} must have a field of view between {:.0} and {:.0}.",
+                    index + 1, crate::MIN_FOV.to_degrees().ceil(), crate::MAX_FOV.to_degrees().floor()
+                ))))
+            }
+            if !distance.is_finite() || distance <= 0. {
+                return Err(Box::from(KeyframesParseError::new(&format!(
+                    "Line {} must have a positive distance.", index + 1
+                ))))
+            }
+
+            keyframes.push(Keyframe {
+                time,
+                view: view::View::new(three::Point::new(x, y, z), yaw.to_radians(), pitch.to_radians(), distance),
+                fov: fov.to_radians()
+            });
+        }
+
+        if keyframes.is_empty() { return Err(Box::from(KeyframesParseError::new("No keyframes."))) }
+        Ok(Keyframes { keyframes })
+    }
+
+    // Time of the last keyframe, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0., |keyframe| keyframe.time)
+    }
+
+    // View and field of view at a time, on a smooth curve through the keyframes.
+    // Holds the first keyframe before it starts, and the last one after it ends.
+    // The curve can overshoot, so the field of view and distance are kept within what the viewer can show.
+    pub fn at(&self, time: f32) -> (view::View, f32) {
+        let keyframes = &self.keyframes;
+        let after = keyframes.partition_point(|keyframe| keyframe.time <= time);
+        if after == 0 { return (keyframes[0].view, keyframes[0].fov) }
+        if after == keyframes.len() { return (keyframes[after - 1].view, keyframes[after - 1].fov) }
+
+        // Curve between the keyframes around the time, repeating the ends for their missing neighbours.
+        let (b, c) = (&keyframes[after - 1], &keyframes[after]);
+        let a = &keyframes[after.saturating_sub(2)];
+        let d = &keyframes[(after + 1).min(keyframes.len() - 1)];
+        let u = (time - b.time) / (c.time - b.time);
+        let curve = |value: fn(&Keyframe) -> f32| catmull_rom(value(a), value(b), value(c), value(d), u);
+
+        let view = view::View::new(
+            three::Point::new(
+                curve(|keyframe| keyframe.view.center.x),
+                curve(|keyframe| keyframe.view.center.y),
+                curve(|keyframe| keyframe.view.center.z)
+            ),
+            curve(|keyframe| keyframe.view.yaw),
+            curve(|keyframe| keyframe.view.pitch),
+            curve(|keyframe| keyframe.view.distance).max(b.view.distance.min(c.view.distance) * MIN_DISTANCE_FRACTION)
+        );
+        (view, curve(|keyframe| keyframe.fov).clamp(crate::MIN_FOV, crate::MAX_FOV))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_keyframes_from_a_file() {
+        let path = env::temp_dir().join(format!("t3d-keyframes-{}", process::id()));
+        fs::write(&path, "# time yaw pitch distance x y z fov\n\n0 0 0 5 0 0 0 90\n2 90 0 10 1 2 3 60\n").unwrap();
+        let keyframes = Keyframes::load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(keyframes.unwrap().duration(), 2.);
+        assert!(Keyframes::load(path.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn parses_keyframes() {
+        let keyframes = Keyframes::parse("# time yaw pitch distance x y z fov\n\n0 0 0 5 0 0 0 90\n2 90 0 10 1 2 3 60\n").unwrap();
+        assert_eq!(keyframes.duration(), 2.);
+
+        let (view, fov) = keyframes.at(2.);
+        assert!((view.yaw - f32::consts::FRAC_PI_2).abs() < 1e-5 && (fov - f32::consts::FRAC_PI_3).abs() < 1e-5);
+        assert!(view.distance == 10. && view.center.x == 1. && view.center.y == 2. && view.center.z == 3.);
+
+        // Halfway, symmetric keyframes meet in the middle, and the ends hold before and after.
+        assert!((keyframes.at(1.).0.distance - 7.5).abs() < 1e-5);
+        assert_eq!(keyframes.at(-1.).0.distance, 5.);
+        assert_eq!(keyframes.at(3.).0.distance, 10.);
+    }
+
+    #[test]
+    fn keeps_overshoot_within_limits() {
+        // Leaving the wide, near keyframes for a far one, the curve swings wider and nearer still.
+        let keyframes = Keyframes::parse("0 0 0 1 0 0 0 160\n1 0 0 1 0 0 0 160\n2 0 0 30 0 0 0 20\n").unwrap();
+        let (view, fov) = keyframes.at(0.5);
+        assert!(view.distance > 0. && fov <= crate::MAX_FOV);
+    }
+
+    // Line of the error parsing the code, as the start of its message.
+    fn error_line(code: &str) -> String {
+        let error = Keyframes::parse(code).err().unwrap().to_string();
+        error.split(" must ").next().unwrap().to_string()
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(error_line("0 0 0 5 0 0 0 90\n1 0 0 5 0 0 0\n"), "Line 2");
+        assert_eq!(error_line("0 0 0 five 0 0 0 90\n"), "Line 1");
+        assert_eq!(error_line("0 0 0 5 0 0 0 170\n"), "Line 1");
+        assert_eq!(error_line("0 0 0 -5 0 0 0 90\n"), "Line 1");
+        assert!(Keyframes::parse("# nothing\n").is_err());
+    }
+
+    #[test]
+    fn rejects_times_out_of_order() {
+        assert_eq!(error_line("0 0 0 5 0 0 0 90\n2 0 0 5 0 0 0 90\n1 0 0 5 0 0 0 90\n"), "Line 3");
+        assert_eq!(error_line("0 0 0 5 0 0 0 90\n0 0 0 5 0 0 0 90\n"), "Line 2");
+    }
+}
@@ -1,122 +1,426 @@
 use std::*;
+use io::Write;
 use process::exit;
 use time::Duration;
 
 use crossterm::{
     event,
     execute,
+    queue,
     terminal,
     style,
     cursor
 };
 
+mod args;
+mod bvh;
+mod bench;
+mod bookmarks;
+mod diff;
+mod keyframes;
 mod screen;
 mod three;
 mod model;
+mod overlay;
+mod remote;
+mod scene;
+mod view;
 
 // Config.
 const VIEWPORT_FOV: f32 = 1.7;
+const MIN_FOV: f32 = 0.2;
+const MAX_FOV: f32 = 2.8;
+const FOV_STEP: f32 = 0.1;
 const VIEWPORT_DISTANCE: f32 = 0.1;
 const TARGET_DURATION_PER_FRAME: Duration = Duration::from_millis(1000 / 60);
+const IDLE_POLL_DURATION: Duration = Duration::from_secs(1);
 const MOUSE_SPEED_MULTIPLIER: f32 = 30.;
 const INITIAL_DISTANCE_MULTIPLIER: f32 = 1.5;
 const SCROLL_MULTIPLER: f32 = 0.03;
 const PAN_MULTIPLIER: f32 = 0.1;
+const KEY_TURN_STEP: f32 = 0.2;
+const KEY_PAN_STEP: f32 = 0.5;
+const FLY_STEP_MULTIPLIER: f32 = 0.03;
+const PRESET_DURATION: Duration = Duration::from_millis(300);
+const ZOOM_TIME_CONSTANT: f32 = 0.05;
+const ZOOM_MIN_STEP_MULTIPLIER: f32 = 0.0005;
+const PICK_RADIUS: f32 = 4.;
+const SNAP_RADIUS: f32 = 12.;
+const DIFF_TOLERANCE_MULTIPLIER: f32 = 0.0001;
+const DRAG_MAX_EDGES: usize = 20000;
+const REMOTE_DRAG_MAX_EDGES: usize = 2000;
+const BENCHMARK_COPIES: usize = 64;
 const HELP_MSG: &str = "\
 \x1b[1mt3d\x1b[0m: Visualize .obj files in the terminal!
 
 \x1b[1mUsage\x1b[0m:
     \"t3d <filepath.obj>\": Interactively view the provided .obj file.
+    \"t3d <a.obj> <b.obj> ...\": View several .obj files together, colored in the order given.
+    \"t3d --diff <old.obj> <new.obj>\": Show removed (red), added (green) and unchanged (grey) geometry.
+    \"t3d --benchmark <filepath.obj>\": Time rendering a grid of copies of the file.
     \"t3d --h\", \"t3d --help\", \"t3d -h\", \"t3d -help\", \"t3d\": Help and info.
     \"t3d --v\", \"t3d --version\", \"t3d -v\", \"t3d -version\": Get version info.
 
+\x1b[1mOptions\x1b[0m:
+    \"--up <x|y|z>\": Axis pointing up in the files (default y). Use z for most CAD exports.
+    \"--layout <grid|overlay>\": Place several files side by side, or on top of each other (default grid).
+    \"--tolerance <distance>\": Distance under which diffed vertices match (default 0.01% of the size).
+    \"--remote\": Tune for slow links, such as SSH over a VPN. Lowers the frame rate to what the link
+        keeps up with, starts in block mode, and draws a simpler model while dragging.
+    \"--threads <count>\": Number of threads to draw large models with (default one per core).
+    \"--keyframes <file>\": Fly the camera through the keyframes in the file when starting, and again with [space].
+        Every line holds a time in seconds, yaw, pitch, distance, center x y z in the first file's coordinates,
+        and field of view. Angles are in degrees.
+        Any other key or click stops flying.
+
 \x1b[1mControls\x1b[0m:
-    Scroll down to zoom out, scroll up to zoom in.
+    Scroll down to zoom out, scroll up to zoom in, towards the point under the mouse.
     Click and drag the mouse to rotate around the model.
     Click and drag the mouse while holding [shift] to pan.
+    Without a mouse, use the arrows or [h], [j], [k] and [l] to rotate, with [shift] to pan,
+        [+] and [-] to zoom, and [r] to return to the initial view.
+    Press [e] to fit the view to the part of the model in it.
+    Press [[] and []] or scroll while holding [ctrl] to narrow or widen the field of view,
+        with [shift] to keep the center the same size on screen, as a dolly zoom.
+    Press [m] and then [1] to [9] to bookmark the view, and ['] and then the same number to return to it.
+        Bookmarks are saved next to the first file, with .t3d appended to its name.
+    Press [t] to switch between turntable and trackball rotation, which turns freely in any direction.
+    Press [,] and [.] to roll the view.
+    Press [f] to switch between orbiting the model and flying, where [w], [a], [s] and [d] or scrolling
+        move the camera, and dragging turns it in place.
+    Press [i] to toggle inertia, where the model keeps turning for a moment after dragging it.
 
     Press [b] to toggle block mode. 
     Press [p] to toggle vertices mode. 
+    Press [c] to toggle color mode.
+    Press [o] to toggle orthographic projection, where parallel lines stay parallel.
+    Press [g] to toggle the axes, with x in red, y in green and z in blue, and a grid on the ground.
+    Press [B] to toggle the bounding box of every model, and show its width, height and depth.
+    Press [M] to toggle measuring, where clicking picks the nearest vertex,
+        and picking two shows the distance between them, and along each axis.
+    Press [x], [y] or [z] to turn the model 90 degrees around that axis.
+    Press [1], [3] or [7] to view the front, right or top, with [ctrl] for the back, left or bottom,
+        [9] to view from the opposite side, and [0] for an isometric view.
 ";
 
-// Disables raw mode and mouse capture, and shows the cursor.
-fn graceful_close() -> ! {
-    execute!(
+// Whether the viewer has taken over the terminal, and it needs restoring.
+static TERMINAL_TAKEN: sync::atomic::AtomicBool = sync::atomic::AtomicBool::new(false);
+
+// Whether the terminal was asked to report keys it can't tell apart otherwise, such as [ctrl] with digits.
+static KEYS_ENHANCED: sync::atomic::AtomicBool = sync::atomic::AtomicBool::new(false);
+
+// Disables raw mode and mouse capture, and shows the cursor, if the viewer took over the terminal.
+// Failures are ignored, as this also runs while panicking.
+fn restore_terminal() {
+    if !TERMINAL_TAKEN.swap(false, sync::atomic::Ordering::SeqCst) { return }
+    if KEYS_ENHANCED.swap(false, sync::atomic::Ordering::SeqCst) {
+        let _ = execute!(io::stdout(), event::PopKeyboardEnhancementFlags);
+    }
+    let _ = execute!(
         io::stdout(),
+        style::ResetColor,
         cursor::Show,
         event::DisableMouseCapture,
-    ).unwrap();
-    terminal::disable_raw_mode().unwrap();
+    );
+    let _ = terminal::disable_raw_mode();
+}
+
+// Puts the terminal into the state the viewer needs, and restores it when dropped.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> io::Result<TerminalGuard> {
+        // Restore the terminal before the panic message is printed, so that it is readable.
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            default_hook(info);
+        }));
+
+        TERMINAL_TAKEN.store(true, sync::atomic::Ordering::SeqCst);
+        terminal::enable_raw_mode()?;
+        let guard = TerminalGuard;
+        execute!(
+            io::stdout(),
+            cursor::Hide,
+            event::EnableMouseCapture,
+        )?;
+
+        // Where the terminal supports it, tell keys apart that it would otherwise send the same, such as [ctrl] and [1].
+        if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            KEYS_ENHANCED.store(true, sync::atomic::Ordering::SeqCst);
+            execute!(
+                io::stdout(),
+                event::PushKeyboardEnhancementFlags(event::KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+            )?;
+        }
+
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+// Restores the terminal and exits successfully.
+fn graceful_close() -> ! {
+    restore_terminal();
     exit(0)
 }
 
-// Closes with the provided error message.
+// Restores the terminal, then exits with the provided error message.
 fn error_close(msg: &dyn fmt::Display) -> ! {
-    execute!(
+    restore_terminal();
+    let _ = execute!(
         io::stderr(),
-        style::Print(msg)
-    ).unwrap();
-    graceful_close()
+        style::Print(format!("{}\n", msg))
+    );
+    exit(1)
+}
+
+// Rotation that turns the given axis to point up (y+).
+fn up_rotation(up: three::Axis) -> three::Quaternion {
+    match up {
+        three::Axis::X => three::Quaternion::from_axis_angle(&three::Axis::Z.unit(), f32::consts::FRAC_PI_2),
+        three::Axis::Y => three::Quaternion::identity(),
+        three::Axis::Z => three::Quaternion::from_axis_angle(&three::Axis::X.unit(), -f32::consts::FRAC_PI_2)
+    }
+}
+
+// Convert a point in the first file's coordinates to world space.
+// Diffs are built from the files already turned upright, so only the later turns are left in their transform.
+fn file_to_world(scene: &scene::Scene, up: three::Axis, diff: bool, point: &three::Point) -> three::Point {
+    let upright = if diff { up_rotation(up).rotate(point) } else { *point };
+    scene.models[0].model_to_world(&upright)
+}
+
+// Convert a point in world space to the first file's coordinates, undoing file_to_world.
+fn world_to_file(scene: &scene::Scene, up: three::Axis, diff: bool, point: &three::Point) -> three::Point {
+    let upright = scene.models[0].world_to_model(point);
+    if diff { up_rotation(up).conjugate().rotate(&upright) } else { upright }
+}
+
+// Places the camera to orbit the center of the view, at its distance and angles.
+fn place_camera(camera: &mut three::Camera, view: &view::View) {
+    camera.coordinates = view.eye();
+    camera.yaw = -view.yaw;
+    camera.pitch = -view.pitch;
+    camera.roll = view.roll;
+}
+
+// Point on a sphere filling the terminal, under the given cell, pointing towards the camera.
+// Points outside the sphere are moved to its edge. See https://en.wikipedia.org/wiki/Arcball.
+fn arcball_point(column: u16, row: u16) -> three::Point {
+    // Cells are about twice as tall as they are wide.
+    let (width, height) = terminal::size().unwrap_or((1, 1));
+    let radius = (width as f32).min(height as f32 * 2.).max(1.) / 2.;
+    let x = (column as f32 + 0.5 - width as f32 / 2.) / radius;
+    let y = (height as f32 / 2. - row as f32 - 0.5) * 2. / radius;
+
+    let length_squared = x * x + y * y;
+    if length_squared <= 1. {
+        three::Point::new(x, y, -(1. - length_squared).sqrt())
+    } else {
+        let length = length_squared.sqrt();
+        three::Point::new(x / length, y / length, 0.)
+    }
+}
+
+// Unrounded screen coordinates of the middle of a terminal cell, leaving out the info message below the screen.
+fn cell_center(camera: &three::Camera, column: u16, row: u16) -> (f32, f32) {
+    let (width, height) = terminal::size().unwrap_or((1, 1));
+    (
+        (column as f32 + 0.5) * camera.screen.width as f32 / width.max(1) as f32,
+        (row as f32 + 0.5) * camera.screen.height as f32 / height.saturating_sub(1).max(1) as f32
+    )
+}
+
+// Point of the scene under a terminal cell, 
+// or where the ray through it crosses the plane of the center, facing the camera, if there is none.
+fn point_under_cursor(
+    camera: &three::Camera, scene: &scene::Scene, view: &view::View, 
+    column: u16, row: u16
+) -> three::Point {
+    if camera.screen.width == 0 || camera.screen.height == 0 { return view.center }
+
+    let (screen_x, screen_y) = cell_center(camera, column, row);
+    let (origin, direction) = camera.ray(screen_x, screen_y);
+
+    // Pick within a few subpixels at the depth of the center.
+    let forward = view.orientation().rotate(&three::Axis::Z.unit());
+    let depth = (
+        (view.center.x - origin.x) * forward.x + 
+        (view.center.y - origin.y) * forward.y + 
+        (view.center.z - origin.z) * forward.z
+    ).max(VIEWPORT_DISTANCE);
+    let tolerance = PICK_RADIUS * camera.subpixel_width(depth) / depth;
+
+    scene.pick(&origin, &direction, tolerance).unwrap_or_else(|| {
+        let along = depth / (direction.x * forward.x + direction.y * forward.y + direction.z * forward.z);
+        three::Point::new(
+            origin.x + direction.x * along, 
+            origin.y + direction.y * along, 
+            origin.z + direction.z * along
+        )
+    })
+}
+
+// Width of the perspective view at a distance of one, for a field of view.
+fn fov_width(fov: f32) -> f32 {
+    2. * (fov / 2.).tan()
+}
+
+// Narrows or widens the field of view by an angle, within limits.
+// With dolly, also moves the camera so that the center stays the same size on screen.
+fn change_fov(camera: &mut three::Camera, view: &mut view::View, angle: f32, dolly: bool) {
+    let fov = (camera.viewport_fov + angle).clamp(MIN_FOV, MAX_FOV);
+    if dolly { view.distance *= fov_width(camera.viewport_fov) / fov_width(fov) }
+    camera.viewport_fov = fov;
+}
+
+// Vertex of the scene that is nearest to a terminal cell on screen, if any is near enough.
+fn vertex_under_cursor(camera: &three::Camera, scene: &scene::Scene, column: u16, row: u16) -> Option<three::Point> {
+    let (screen_x, screen_y) = cell_center(camera, column, row);
+    scene.models.iter()
+        .filter_map(|model| camera.nearest_point(model, screen_x, screen_y, SNAP_RADIUS))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, point)| point)
+}
+
+// Moves the camera towards the target by the given distance, or away from it if negative,
+// scaling the view around the target so that it stays in place on screen.
+// When orthographic, narrows or widens the view as much as moving would at a distance of one.
+fn zoom(camera: &mut three::Camera, view: &mut view::View, distance: f32, target: &three::Point) {
+    let fov_width = fov_width(camera.viewport_fov);
+
+    // Go at most half way at once, so that the camera never reaches the target, and the view never collapses.
+    let scale = match &mut camera.orthographic_width {
+        Some(width) => {
+            let scale = (1. + distance * fov_width / *width).max(0.5);
+            *width *= scale;
+            scale
+        }
+        None => {
+            let eye = view.eye();
+            let to_target = (
+                (target.x - eye.x).powi(2) + (target.y - eye.y).powi(2) + (target.z - eye.z).powi(2)
+            ).sqrt();
+            if to_target == 0. { return }
+            (1. + distance / to_target).max(0.5)
+        }
+    };
+    view.scale_around(target, scale);
+}
+
+// Returns the center and diagonal length of the scene's world bounds.
+fn frame_scene(scene: &scene::Scene) -> (three::Point, f32) {
+    let bounds = scene.world_bounds();
+    (three::bounds_center(&bounds), three::bounds_diagonal(&bounds))
 }
 
 fn main() {
     // Parse arguments.
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 2 { error_close(&"Please supply only one file path to visualize.") }
-    if args.is_empty() { error_close(&"Error parsing arguments.") }
-
-    let help_mode = args.len() == 1 || 
-        ["-h", "-help", "--h", "--help"].map(String::from).contains(&args[1]);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args = match args::Args::parse(&args) {
+        Ok(args) => args,
+        Err(error) => error_close(&error)
+    };
 
-    if help_mode {
+    if args.mode == args::Mode::Help {
         execute!(
             io::stdout(),
             style::Print(HELP_MSG)
         ).unwrap();
-        graceful_close();
+        exit(0);
     }
 
-    let version_mode = args.len() == 1 || 
-        ["-v", "-version", "--v", "--version"].map(String::from).contains(&args[1]);
-
-    if version_mode {
+    if args.mode == args::Mode::Version {
         execute!(
             io::stdout(),
             style::Print(env!("CARGO_PKG_VERSION"))
         ).unwrap();
-        graceful_close();
+        exit(0);
     }
 
-    terminal::enable_raw_mode().unwrap();
-    execute!(
-        io::stdout(),
-        cursor::Hide,
-        event::EnableMouseCapture,
-    ).unwrap();
-
-    let file_path = &args[1];
-
-    // Load model.
-    let input_model = match model::Model::new_obj(
-        file_path,
-        three::Point::new(0., 0., 0.)
-    ) {
-        Ok(model) => model,
-        Err(error) => error_close(&error)
+    // Load models, and turn them so that their up axis points up.
+    let mut models = Vec::<model::Model>::new();
+    for file_path in args.file_paths.iter() {
+        let mut model = match model::Model::new_obj(
+            file_path,
+            three::Transform::identity()
+        ) {
+            Ok(model) => model,
+            Err(error) => error_close(&format!("{}: {}", file_path, error))
+        };
+        model.rotate(&up_rotation(args.up));
+        models.push(model);
+    }
+
+    let keyframes = args.keyframes.as_ref().map(|path| match keyframes::Keyframes::load(path) {
+        Ok(keyframes) => keyframes,
+        Err(error) => error_close(&format!("{}: {}", path, error))
+    });
+
+    // In diff mode, replace the two models with their differences, overlaid.
+    let mut diff_msg = None;
+    if args.diff {
+        let new = models.pop().unwrap();
+        let old = models.pop().unwrap();
+        let tolerance = args.tolerance.unwrap_or_else(|| {
+            let size = f32::max(
+                three::bounds_diagonal(&old.world_bounds()), 
+                three::bounds_diagonal(&new.world_bounds())
+            );
+            size * DIFF_TOLERANCE_MULTIPLIER
+        });
+        let diff = diff::Diff::new(&old, &new, tolerance);
+
+        diff_msg = Some(format!(
+            "diff: -{} +{} edges, -{} +{} vertices",
+            diff.removed.edges.len(), diff.added.edges.len(),
+            diff.removed.points.len(), diff.added.points.len()
+        ));
+        models = diff.into_models();
+    }
+
+    // When benchmarking, scale up the first model by making a grid of copies.
+    if args.mode == args::Mode::Benchmark {
+        models = vec![models.swap_remove(0); BENCHMARK_COPIES];
+    }
+
+    let layout = match args.mode {
+        args::Mode::Benchmark => scene::Layout::Grid,
+        _ if args.diff => scene::Layout::Overlay,
+        _ => args.layout
+    };
+    let mut scene = scene::Scene::new(models, layout);
+
+    if args.mode == args::Mode::Benchmark {
+        let (center, diagonal) = frame_scene(&scene);
+        let mut camera = three::Camera::new(center, 0., 0., 0., VIEWPORT_DISTANCE, VIEWPORT_FOV);
+        if let Some(threads) = args.threads { camera.threads = threads }
+        place_camera(&mut camera, &view::View::new(center, 0., 0., diagonal * INITIAL_DISTANCE_MULTIPLIER));
+
+        execute!(
+            io::stdout(),
+            style::Print(bench::run(&scene, &mut camera))
+        ).unwrap();
+        exit(0);
+    }
+
+    // Only take over the terminal once everything is loaded.
+    let _terminal_guard = match TerminalGuard::new() {
+        Ok(guard) => guard,
+        Err(error) => error_close(&format!("Could not set up the terminal: {}", error))
     };
 
     // Get dimensions.
-    let bounds = input_model.world_bounds();
-    let mut center = input_model.model_to_world(&three::Point::new(
-        (bounds.0.x + bounds.1.x) / 2., 
-        (bounds.0.y + bounds.1.y) / 2., 
-        (bounds.0.z + bounds.1.z) / 2., 
-    ));
-    let diagonal = (
-        (bounds.0.x - bounds.1.x).powi(2) +
-        (bounds.0.y - bounds.1.y).powi(2) +
-        (bounds.0.z - bounds.1.z).powi(2)
-    ).sqrt();
+    let (center, mut diagonal) = frame_scene(&scene);
+    let mut scene_bounds = scene.world_bounds();
+    let mut model_bounds: Vec<_> = scene.models.iter().map(|model| model.world_bounds()).collect();
 
     // Setup camera.
     let mut camera = three::Camera::new(
@@ -125,117 +429,533 @@ fn main() {
         VIEWPORT_DISTANCE, VIEWPORT_FOV,
     );
 
+    if let Some(threads) = args.threads { camera.threads = threads }
+
+    // Block mode has fewer distinct glyphs, so it changes fewer cells over slow links.
+    let mut braile_mode = !args.remote;
+
     // Ensure camera.braille_mode matches the default braile_mode
-    camera.braille_mode = true;
+    camera.braille_mode = braile_mode;
 
     // Setup viewer params (relative to model).
-    let mut view_yaw: f32 = 0.0;
-    let mut view_pitch: f32 = 0.0;
-    let mut distance_to_model = diagonal * INITIAL_DISTANCE_MULTIPLIER;
+    let mut view = view::View::new(center, 0., 0., diagonal * INITIAL_DISTANCE_MULTIPLIER);
+    let mut transition: Option<view::Transition> = None;
+
+    // Turning carried on after dragging, and zooming still to ease into.
+    let mut inertia = view::Inertia::new();
+    let mut coasting = false;
+    let mut pending_zoom = 0.;
+
+    // Point to zoom towards, or the center if None.
+    let mut zoom_target: Option<three::Point> = None;
+    let mut last_step = time::Instant::now();
 
     // Render modes.
     let mut points_mode = false;
-    let mut braile_mode = true;
-    let mut pan_mode = false;
+    let mut trackball_mode = false;
+    let mut fly_mode = false;
+    let mut inertia_mode = false;
+    let mut guides_mode = false;
+    let mut bounds_mode = false;
+    let mut measure_mode = false;
+
+    // Vertices picked to measure between, in world space.
+    let mut picks = Vec::<three::Point>::new();
+    let mut color_mode = true;
+
+    // Setup frame timing, starting with a frame due immediately.
+    let mut dirty = true;
+    let mut last_frame_start = time::Instant::now() - TARGET_DURATION_PER_FRAME;
+    let mut frame_limiter = remote::FrameLimiter::new(TARGET_DURATION_PER_FRAME);
+    let mut last_msg = String::new();
+
+    // Message about the last action, shown until the next key is pressed.
+    let mut notice: Option<String> = None;
+
+    // Views bookmarked for the first file, and the key starting a bookmark action, waiting for its number.
+    let mut bookmarks = bookmarks::Bookmarks::new(&args.file_paths[0]);
+    if let Err(error) = bookmarks.load() { notice = Some(format!("could not load bookmarks: {}", error)) }
+    let mut bookmark_key: Option<char> = None;
+
+    // When the camera started flying through the keyframes, while it is.
+    let mut playback = keyframes.as_ref().map(|_| time::Instant::now());
 
     // Setup events.
-    let mut mouse_speed: (f32, f32) = (0., 0.);
     let mut last_mouse_position = screen::Point::new(0, 0);
+    let mut press_position = screen::Point::new(0, 0);
+    let mut dragging = false;
 
     // Start main loop.
     loop {
-        let start = time::Instant::now();
-        let mut start_mouse_position = last_mouse_position;
+        // While idle, block until something happens. 
+        // Otherwise only wait for the rest of the frame, to keep up with dragging and transitions.
+        let animating = transition.is_some() || playback.is_some() || coasting || pending_zoom != 0.;
+        let timeout = if dirty || animating { 
+            frame_limiter.frame_duration().saturating_sub(last_frame_start.elapsed()) 
+        } else { 
+            IDLE_POLL_DURATION 
+        };
+
+        // Rotation and panning asked for since the last frame, from the mouse or keys.
+        let mut turn: (f32, f32) = (0., 0.);
+        let mut spin = three::Quaternion::identity();
+        let mut pan: (f32, f32) = (0., 0.);
+
+        // Steps to fly forwards and to the right since the last frame.
+        let mut fly: (f32, f32) = (0., 0.);
+
+        // Turning by dragging since the last frame, in the form inertia keeps it.
+        let mut drag_turn: Option<three::Point> = None;
 
         // Look through the queue while there is an available event.
-        let mut event_count = 0;
-        while event::poll(Duration::from_secs(0)).unwrap() {
+        let mut has_event = event::poll(timeout).unwrap();
+        while has_event {
             if let Ok(event) = event::read() {
                 match event {
+                    // Save or return to a bookmark, with the number following [m] or ['].
+                    event::Event::Key(event::KeyEvent { code: event::KeyCode::Char(digit @ '1'..='9'), .. }) 
+                        if bookmark_key.is_some() => {
+                        let slot = digit.to_digit(10).unwrap();
+
+                        if bookmark_key.take() == Some('m') {
+                            // Keep the center in the file's coordinates, which don't depend on the layout or turns.
+                            let center = world_to_file(&scene, args.up, args.diff, &view.center);
+                            let bookmark = bookmarks::Bookmark {
+                                view: view::View { center, ..view },
+                                fov: camera.viewport_fov,
+                                orthographic_width: camera.orthographic_width,
+                                points_mode,
+                                braille_mode: braile_mode,
+                                color_mode
+                            };
+                            notice = Some(match bookmarks.set(slot, bookmark) {
+                                Ok(()) => format!("saved bookmark {}", slot),
+                                Err(error) => format!("could not save bookmark {}: {}", slot, error)
+                            });
+                        } else if let Some(bookmark) = bookmarks.get(slot) {
+                            let center = file_to_world(&scene, args.up, args.diff, &bookmark.view.center);
+                            let target = view::View { center, ..bookmark.view };
+                            transition = Some(view::Transition::new(view, target, PRESET_DURATION));
+                            camera.viewport_fov = bookmark.fov;
+                            camera.orthographic_width = bookmark.orthographic_width;
+                            points_mode = bookmark.points_mode;
+                            braile_mode = bookmark.braille_mode;
+                            camera.braille_mode = braile_mode;
+                            color_mode = bookmark.color_mode;
+                            camera.screen.color_mode = color_mode;
+                            notice = Some(format!("bookmark {}", slot));
+                        } else {
+                            notice = Some(format!("no bookmark {}", slot));
+                        }
+                        dirty = true;
+                    }
+
                     event::Event::Key(key_event) => {
                         let is_ctrl_c = key_event.modifiers == event::KeyModifiers::CONTROL
                             && key_event.code == event::KeyCode::Char('c');
 
                         if is_ctrl_c { graceful_close() }
+
+                        // Any other key clears the last notice, and cancels a bookmark action.
+                        notice = None;
+                        bookmark_key = None;
+
+                        // Fly through the keyframes again with [space], and stop flying with any other key.
+                        playback = match (key_event.code, &keyframes) {
+                            (event::KeyCode::Char(' '), Some(_)) => {
+                                transition = None;
+                                Some(time::Instant::now())
+                            }
+                            _ => None
+                        };
+                        match key_event.code {
+                            event::KeyCode::Char('m') => {
+                                bookmark_key = Some('m');
+                                notice = Some("press [1] to [9] to save a bookmark".to_string());
+                            }
+                            event::KeyCode::Char('\'') => {
+                                bookmark_key = Some('\'');
+                                notice = Some("press [1] to [9] to return to a bookmark".to_string());
+                            }
+                            _ => {}
+                        }
                         if key_event.code == event::KeyCode::Char('p') { points_mode = !points_mode }
                         if key_event.code == event::KeyCode::Char('b') { 
                             braile_mode = !braile_mode;
                             camera.braille_mode = braile_mode;
                         }
+
+                        if key_event.code == event::KeyCode::Char('c') { 
+                            color_mode = !color_mode;
+                            camera.screen.color_mode = color_mode;
+                        }
+
+                        // Switch projection, keeping the center of the view the same size on screen.
+                        if key_event.code == event::KeyCode::Char('o') {
+                            camera.orthographic_width = match camera.orthographic_width {
+                                Some(width) => {
+                                    view.distance = width / fov_width(camera.viewport_fov);
+                                    None
+                                }
+                                None => Some(view.distance * fov_width(camera.viewport_fov))
+                            };
+                        }
+
+                        // Turn to a standard view of the scene, from the other side while holding [ctrl],
+                        // or to the other side of the current one.
+                        let ctrl = key_event.modifiers.contains(event::KeyModifiers::CONTROL);
+                        let preset = match key_event.code {
+                            event::KeyCode::Char('1') if ctrl => Some(view::Preset::Back),
+                            event::KeyCode::Char('3') if ctrl => Some(view::Preset::Left),
+                            event::KeyCode::Char('7') if ctrl => Some(view::Preset::Bottom),
+                            event::KeyCode::Char('1') => Some(view::Preset::Front),
+                            event::KeyCode::Char('3') => Some(view::Preset::Right),
+                            event::KeyCode::Char('7') => Some(view::Preset::Top),
+                            event::KeyCode::Char('0') => Some(view::Preset::Isometric),
+                            _ => None
+                        };
+                        if let Some(preset) = preset {
+                            let (center, diagonal) = frame_scene(&scene);
+                            let target = preset.view(center, diagonal * INITIAL_DISTANCE_MULTIPLIER);
+                            transition = Some(view::Transition::new(view, target, PRESET_DURATION));
+                        }
+                        if key_event.code == event::KeyCode::Char('9') {
+                            // Flip where a running transition is heading, rather than where it has got to.
+                            let current = transition.as_ref().map_or(view, |transition| transition.target());
+                            transition = Some(view::Transition::new(view, current.opposite(), PRESET_DURATION));
+                        }
+
+                        // Orbit with the arrows or [h], [j], [k] and [l], and pan while holding [shift],
+                        // moving the model the same way as dragging it would.
+                        let shift = key_event.modifiers.contains(event::KeyModifiers::SHIFT);
+                        let step = match key_event.code {
+                            event::KeyCode::Left | event::KeyCode::Char('h' | 'H') => Some((-1., 0.)),
+                            event::KeyCode::Right | event::KeyCode::Char('l' | 'L') => Some((1., 0.)),
+                            event::KeyCode::Up | event::KeyCode::Char('k' | 'K') => Some((0., 1.)),
+                            event::KeyCode::Down | event::KeyCode::Char('j' | 'J') => Some((0., -1.)),
+                            _ => None
+                        };
+                        if let Some(step) = step {
+                            transition = None;
+                            if shift {
+                                pan.0 += step.0 * KEY_PAN_STEP;
+                                pan.1 += step.1 * KEY_PAN_STEP;
+                            } else {
+                                turn.0 += step.0 * KEY_TURN_STEP;
+                                turn.1 += step.1 * KEY_TURN_STEP;
+                            }
+                        }
+
+                        // Roll with [,] and [.].
+                        let roll = match key_event.code {
+                            event::KeyCode::Char(',') => Some(-KEY_TURN_STEP),
+                            event::KeyCode::Char('.') => Some(KEY_TURN_STEP),
+                            _ => None
+                        };
+                        if let Some(roll) = roll {
+                            transition = None;
+                            view.roll += roll;
+                        }
+
+                        if key_event.code == event::KeyCode::Char('t') { 
+                            trackball_mode = !trackball_mode;
+                            coasting = false;
+                        }
+                        if key_event.code == event::KeyCode::Char('i') { inertia_mode = !inertia_mode }
+                        if key_event.code == event::KeyCode::Char('g') { guides_mode = !guides_mode }
+                        if key_event.code == event::KeyCode::Char('B') { bounds_mode = !bounds_mode }
+                        if key_event.code == event::KeyCode::Char('M') { 
+                            measure_mode = !measure_mode;
+                            picks.clear();
+                        }
+                        if key_event.code == event::KeyCode::Char('f') { fly_mode = !fly_mode }
+
+                        // Fly with [w], [a], [s] and [d].
+                        let fly_step = match key_event.code {
+                            event::KeyCode::Char('w') => Some((1., 0.)),
+                            event::KeyCode::Char('s') => Some((-1., 0.)),
+                            event::KeyCode::Char('a') => Some((0., -1.)),
+                            event::KeyCode::Char('d') => Some((0., 1.)),
+                            _ => None
+                        };
+                        if let (Some(fly_step), true) = (fly_step, fly_mode) {
+                            transition = None;
+                            fly.0 += fly_step.0;
+                            fly.1 += fly_step.1;
+                        }
+
+                        // Zoom with [+] and [-], with or without [shift].
+                        let zoom_step = match key_event.code {
+                            event::KeyCode::Char('+' | '=') => Some(-1.),
+                            event::KeyCode::Char('-' | '_') => Some(1.),
+                            _ => None
+                        };
+                        if let Some(zoom_step) = zoom_step {
+                            transition = None;
+                            pending_zoom += zoom_step * diagonal * SCROLL_MULTIPLER;
+                            zoom_target = None;
+                        }
+
+                        // Fit the view to what is in it, or to the whole scene if nothing is.
+                        if key_event.code == event::KeyCode::Char('e') {
+                            let (center, diagonal) = match scene.visible_bounds(&camera) {
+                                Some(bounds) if three::bounds_diagonal(&bounds) > 0. => 
+                                    (three::bounds_center(&bounds), three::bounds_diagonal(&bounds)),
+                                _ => frame_scene(&scene)
+                            };
+                            let target = view::View { center, distance: diagonal * INITIAL_DISTANCE_MULTIPLIER, ..view };
+                            if let Some(width) = &mut camera.orthographic_width { 
+                                *width = target.distance * fov_width(camera.viewport_fov) 
+                            }
+                            transition = Some(view::Transition::new(view, target, PRESET_DURATION));
+                        }
+
+                        // Change the field of view, or do a dolly zoom with [shift].
+                        let fov_step = match key_event.code {
+                            event::KeyCode::Char('[') => Some((-FOV_STEP, false)),
+                            event::KeyCode::Char(']') => Some((FOV_STEP, false)),
+                            event::KeyCode::Char('{') => Some((-FOV_STEP, true)),
+                            event::KeyCode::Char('}') => Some((FOV_STEP, true)),
+                            _ => None
+                        };
+                        if let Some((angle, dolly)) = fov_step {
+                            transition = None;
+                            change_fov(&mut camera, &mut view, angle, dolly);
+                        }
+
+                        // Return to the view the viewer starts with.
+                        if key_event.code == event::KeyCode::Char('r') {
+                            camera.viewport_fov = VIEWPORT_FOV;
+                            let (center, diagonal) = frame_scene(&scene);
+                            let target = view::View::new(center, 0., 0., diagonal * INITIAL_DISTANCE_MULTIPLIER);
+                            if let Some(width) = &mut camera.orthographic_width { 
+                                *width = target.distance * fov_width(camera.viewport_fov) 
+                            }
+                            transition = Some(view::Transition::new(view, target, PRESET_DURATION));
+                        }
+
+                        // Reorient the models, and recenter on their new bounds.
+                        let axis = match key_event.code {
+                            event::KeyCode::Char('x') => Some(three::Axis::X),
+                            event::KeyCode::Char('y') => Some(three::Axis::Y),
+                            event::KeyCode::Char('z') => Some(three::Axis::Z),
+                            _ => None
+                        };
+                        if let Some(axis) = axis {
+                            scene.rotate(&three::Quaternion::from_axis_angle(
+                                &axis.unit(), 
+                                f32::consts::FRAC_PI_2
+                            ));
+                            (view.center, diagonal) = frame_scene(&scene);
+                            scene_bounds = scene.world_bounds();
+                            model_bounds = scene.models.iter().map(|model| model.world_bounds()).collect();
+
+                            // Picks stay where the vertices were, so start measuring over.
+                            picks.clear();
+                        }
+
+                        dirty = true;
                     }
 
                     // Mouse controls.
                     event::Event::Mouse(mouse_event) => {
                         let (x, y) = (mouse_event.column, mouse_event.row);
+                        if mouse_event.kind != event::MouseEventKind::Moved { playback = None }
                         match mouse_event.kind {
 
                             // If the mouse has been pressed, record this position.
                             event::MouseEventKind::Down(_) => {
-                                pan_mode = mouse_event.modifiers == event::KeyModifiers::SHIFT;
                                 last_mouse_position.x = x as i32;
                                 last_mouse_position.y = y as i32;
-                                start_mouse_position = last_mouse_position;
-                                event_count += 1;
+                                press_position = last_mouse_position;
+                                dragging = true;
+                                transition = None;
+                                coasting = false;
+                                inertia.stop();
                             }
 
-                            // If the mouse is dragged, update drag speeds.
+                            // Redraw in full detail once the mouse is released.
+                            event::MouseEventKind::Up(_) => {
+                                dragging = false;
+                                if let Some(drag_turn) = drag_turn.take() { inertia.record(&drag_turn) }
+                                coasting = inertia_mode && inertia.release();
+
+                                // When measuring, a click without dragging picks a vertex, starting over after two.
+                                if measure_mode && (press_position.x, press_position.y) == (x as i32, y as i32) {
+                                    match vertex_under_cursor(&camera, &scene, x, y) {
+                                        Some(vertex) => {
+                                            if picks.len() == 2 { picks.clear() }
+                                            picks.push(vertex);
+                                            notice = None;
+                                        }
+                                        None => notice = Some("no vertex near the click".to_string())
+                                    }
+                                }
+                                dirty = true;
+                            }
+
+                            // If the mouse is dragged, accumulate the movement.
                             event::MouseEventKind::Drag(_) => {
-                                pan_mode = mouse_event.modifiers == event::KeyModifiers::SHIFT;
-                                let delta_x = x as f32 - start_mouse_position.x as f32;
-                                let delta_y = start_mouse_position.y as f32 - y as f32;
-                                mouse_speed.0 = delta_x / camera.screen.width as f32 * MOUSE_SPEED_MULTIPLIER;
-                                mouse_speed.1 = delta_y / camera.screen.width as f32 * MOUSE_SPEED_MULTIPLIER;
+                                let speed = MOUSE_SPEED_MULTIPLIER / camera.screen.width.max(1) as f32;
+                                let delta = (
+                                    (x as f32 - last_mouse_position.x as f32) * speed,
+                                    (last_mouse_position.y as f32 - y as f32) * speed
+                                );
+                                let turned = if mouse_event.modifiers == event::KeyModifiers::SHIFT {
+                                    pan.0 += delta.0;
+                                    pan.1 += delta.1;
+                                    None
+                                } else if trackball_mode {
+                                    // Turn the camera the opposite way to the point under the mouse,
+                                    // so that the model follows the mouse.
+                                    let from = arcball_point(last_mouse_position.x as u16, last_mouse_position.y as u16);
+                                    let rotation = three::Quaternion::between(&arcball_point(x, y), &from);
+                                    spin = spin.mul(&rotation);
+                                    Some(rotation.rotation_vector())
+                                } else {
+                                    turn.0 += delta.0;
+                                    turn.1 += delta.1;
+                                    Some(three::Point::new(delta.0, delta.1, 0.))
+                                };
+
+                                // Small turns add up about the same in either form.
+                                if let Some(turned) = turned {
+                                    let total = drag_turn.unwrap_or(three::Point::new(0., 0., 0.));
+                                    drag_turn = Some(three::Point::new(total.x + turned.x, total.y + turned.y, total.z + turned.z));
+                                }
                                 last_mouse_position.x = x as i32;
                                 last_mouse_position.y = y as i32;
-                                event_count += 1;
+                                dirty = true;
+                            }
+
+                            // Widen the field of view while holding [ctrl], and do a dolly zoom with [shift] too.
+                            event::MouseEventKind::ScrollDown | event::MouseEventKind::ScrollUp 
+                                if mouse_event.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                                transition = None;
+                                let angle = if mouse_event.kind == event::MouseEventKind::ScrollDown { FOV_STEP } else { -FOV_STEP };
+                                let dolly = mouse_event.modifiers.contains(event::KeyModifiers::SHIFT);
+                                change_fov(&mut camera, &mut view, angle, dolly);
+                                dirty = true;
                             }
 
+                            // Zoom out from the point under the mouse, or fly backwards.
                             event::MouseEventKind::ScrollDown => {
-                                // Zoom out.
-                                distance_to_model += diagonal * SCROLL_MULTIPLER;
+                                transition = None;
+                                if fly_mode { fly.0 -= 1. } else { 
+                                    pending_zoom += diagonal * SCROLL_MULTIPLER;
+                                    zoom_target = Some(point_under_cursor(&camera, &scene, &view, x, y));
+                                }
+                                dirty = true;
                             }
 
+                            // Zoom in towards the point under the mouse, or fly forwards.
                             event::MouseEventKind::ScrollUp => {
-                                // Zoom in.
-                                distance_to_model -= diagonal * SCROLL_MULTIPLER;
-                                distance_to_model = distance_to_model.max(0.);
+                                transition = None;
+                                if fly_mode { fly.0 += 1. } else { 
+                                    pending_zoom -= diagonal * SCROLL_MULTIPLER;
+                                    zoom_target = Some(point_under_cursor(&camera, &scene, &view, x, y));
+                                }
+                                dirty = true;
                             }
                             _ => {}
                         }
                     }
+
+                    // The terminal may have reflowed or cleared the old frame.
+                    event::Event::Resize(_, _) => {
+                        camera.screen.invalidate();
+                        last_msg.clear();
+                        dirty = true;
+                    }
                     _ => {}
                 }
             }
+            has_event = event::poll(Duration::from_secs(0)).unwrap();
         }
 
-        // If no event happened, reset the mouse.
-        if event_count == 0 { 
-            mouse_speed = (0., 0.);
-            pan_mode = false;
-         }
-
         // Update viewer params.
-        if pan_mode {
+        // Handle pan, along the camera's own axes so that it follows roll too.
+        view.translate(-pan.0 * diagonal * PAN_MULTIPLIER, -pan.1 * diagonal * PAN_MULTIPLIER, 0.);
 
-            // Handle horizontal pan.
-            center.x -= mouse_speed.0 * camera.yaw.cos() * diagonal * PAN_MULTIPLIER;
-            center.z += mouse_speed.0 * camera.yaw.sin() * diagonal * PAN_MULTIPLIER;
+        // Follow the speed of dragging, and keep turning after it ends.
+        if let Some(drag_turn) = drag_turn { inertia.record(&drag_turn) }
+        if coasting {
+            match inertia.step() {
+                Some(step) if trackball_mode => {
+                    let angle = (step.x.powi(2) + step.y.powi(2) + step.z.powi(2)).sqrt();
+                    spin = spin.mul(&three::Quaternion::from_axis_angle(&step, angle));
+                }
+                Some(step) => {
+                    turn.0 += step.x;
+                    turn.1 += step.y;
+                }
+                None => coasting = false
+            }
+            dirty = true;
+        }
 
-            // Handle vertical pan.
-            center.y -= mouse_speed.1 * camera.pitch.cos() * diagonal * PAN_MULTIPLIER;
-            center.x += mouse_speed.1 * camera.yaw.sin() * camera.pitch.sin() * diagonal * PAN_MULTIPLIER;
-            center.z += mouse_speed.1 * camera.yaw.cos() * camera.pitch.sin() * diagonal * PAN_MULTIPLIER;
+        // Ease into zooming, going part of the remaining way each frame, or all at once over slow links.
+        let step_duration = last_step.elapsed().min(frame_limiter.frame_duration()).as_secs_f32();
+        last_step = time::Instant::now();
+        if pending_zoom != 0. {
+            let part = if args.remote || pending_zoom.abs() < diagonal * ZOOM_MIN_STEP_MULTIPLIER {
+                pending_zoom
+            } else {
+                pending_zoom * (1. - (-step_duration / ZOOM_TIME_CONSTANT).exp())
+            };
+            let target = zoom_target.unwrap_or(view.center);
+            zoom(&mut camera, &mut view, part, &target);
+            pending_zoom -= part;
+            dirty = true;
+        }
+
+        // Turn around the axes of the screen as a trackball, or around the vertical and then the horizontal.
+        // When flying, turn around the camera instead of the center.
+        let eye = view.eye();
+        if trackball_mode {
+            let axis = three::Point::new(-turn.1, turn.0, 0.);
+            let rotation = spin.mul(&three::Quaternion::from_axis_angle(&axis, turn.0.hypot(turn.1)));
+
+            // Skip converting the angles back and forth when there is nothing to turn.
+            if rotation.w < 1. { view.rotate(&rotation) }
         } else {
-            view_yaw -= mouse_speed.0;
-            view_pitch -= mouse_speed.1;
+            view.yaw -= turn.0;
+            view.pitch -= turn.1;
+        }
+        if fly_mode { view.set_eye(&eye) }
+
+        // Fly at a speed relative to the size of the scene.
+        view.translate(fly.1 * diagonal * FLY_STEP_MULTIPLIER, 0., fly.0 * diagonal * FLY_STEP_MULTIPLIER);
+
+        // Move along the transition to a preset, until it ends.
+        if let Some(current) = &transition {
+            let finished;
+            (view, finished) = current.view();
+            if finished { transition = None }
+            dirty = true;
         }
 
+        // Follow the keyframes, until the last one.
+        if let (Some(start), Some(keyframes)) = (playback, &keyframes) {
+            let time = start.elapsed().as_secs_f32();
+            (view, camera.viewport_fov) = keyframes.at(time);
+            view.center = file_to_world(&scene, args.up, args.diff, &view.center);
+            if time >= keyframes.duration() { playback = None }
+            dirty = true;
+        }
+
+        // Only render when something changed, and the next frame is due.
+        if !dirty || last_frame_start.elapsed() < frame_limiter.frame_duration() { continue }
+        last_frame_start = time::Instant::now();
+        dirty = false;
+
         // Update camera position.
-        camera.coordinates.z = -view_yaw.cos() * view_pitch.cos() * distance_to_model + center.z;
-        camera.coordinates.x = view_yaw.sin() * view_pitch.cos() * distance_to_model + center.x;
-        camera.coordinates.y = view_pitch.sin() * distance_to_model + center.y;
-        camera.yaw = -view_yaw;
-        camera.pitch = -view_pitch;
+        // Orthographic views don't change with distance, so stay back far enough to see the whole scene.
+        let distance = match camera.orthographic_width {
+            Some(_) => diagonal * INITIAL_DISTANCE_MULTIPLIER,
+            None => view.distance
+        };
+        place_camera(&mut camera, &view::View { distance, ..view });
 
         // Render.
         if braile_mode { camera.screen.fit_to_terminal::<screen::BrailePixel>()  }
@@ -243,16 +963,31 @@ fn main() {
 
         camera.screen.clear();
 
-        if points_mode { camera.plot_model_points(&input_model) }
-        else { camera.plot_model_edges(&input_model) }
+        // Draw the ground first, so that the models are drawn over it.
+        if guides_mode { overlay::draw_grid(&mut camera, &scene_bounds) }
 
-        if braile_mode { camera.screen.render::<screen::BrailePixel>() }
-        else { camera.screen.render::<screen::BlockPixel>() }
-
-        // Add buffer time to hit 60 fps.
-        if let Some(time) = TARGET_DURATION_PER_FRAME.checked_sub(start.elapsed()) { 
-            thread::sleep(time);
+        // While dragging, draw a simplified model, even more so over slow links.
+        let max_edges = if args.remote { REMOTE_DRAG_MAX_EDGES } else { DRAG_MAX_EDGES };
+        for model in scene.models.iter() {
+            match (points_mode, dragging) {
+                (true, false) => camera.plot_model_points(model),
+                (false, false) => camera.plot_model_edges(model),
+                (true, true) => camera.plot_model_points_coarse(model, max_edges),
+                (false, true) => camera.plot_model_edges_coarse(model, max_edges),
+            }
         }
+        if bounds_mode {
+            for (model, bounds) in scene.models.iter().zip(model_bounds.iter()) {
+                if !model.is_empty() { overlay::draw_box(&mut camera, bounds, model.color) }
+            }
+        }
+        if measure_mode { overlay::draw_measurement(&mut camera, &picks) }
+        if guides_mode { overlay::draw_axes(&mut camera, distance) }
+
+        // Queue the changes to the screen, and the info message below it, then send them at once.
+        let mut frame = Vec::<u8>::new();
+        if braile_mode { camera.screen.render::<screen::BrailePixel>(&mut frame).unwrap() }
+        else { camera.screen.render::<screen::BlockPixel>(&mut frame).unwrap() }
 
         // Create info message variants for responsive resizing.
         let points_mode_msg = format!(
@@ -265,35 +1000,106 @@ fn main() {
             if braile_mode {"braile"} else {"blocks"}
         );
 
-        let fps_msg = format!(
-            "fps: {:3.0}", 1. / start.elapsed().as_secs_f32()
+        let projection_msg = format!(
+            "projection: {}",
+            if camera.orthographic_width.is_some() {"orthographic"} else {"perspective"}
         );
 
+        let fov_msg = format!("fov: {:.0}°", camera.viewport_fov.to_degrees());
+
+        let grid_msg = match guides_mode {
+            true => format!(" | grid: {}", overlay::format_length(overlay::grid_spacing(&scene_bounds), 1)),
+            false => String::new()
+        };
+
+        // Time taken to draw this frame, as frames are only drawn when something changes.
+        // Over slow links, show the measured bandwidth instead, which changes less often.
+        let frame_time_msg = match frame_limiter.throughput() {
+            Some(throughput) if args.remote => format!("link: {:.0} kB/s", throughput / 1000.),
+            _ if args.remote => "link: fast".to_string(),
+            _ => format!("frame time: {:3.0} ms", last_frame_start.elapsed().as_secs_f32() * 1000.)
+        };
+
         let resolution_msg = format!(
             "resolution: {} x {}",
             camera.screen.width,
             camera.screen.height,
         );
 
+        // Width, height and depth of every model, when showing their bounding boxes.
+        let size_msg = match bounds_mode {
+            true => Some(format!("size: {}", model_bounds.iter().zip(scene.models.iter())
+                .filter(|(_, model)| !model.is_empty())
+                .map(|((min, max), _)| [max.x - min.x, max.y - min.y, max.z - min.z]
+                    .map(|length| overlay::format_length(length, 4))
+                    .join(" x "))
+                .collect::<Vec<_>>()
+                .join(", "))),
+            false => None
+        };
+
+        // Distance between the picked vertices, and along each axis, when measuring.
+        let measure_msg = match (measure_mode, picks.as_slice()) {
+            (false, _) => None,
+            (true, []) => Some("measure: click a vertex".to_string()),
+            (true, [_]) => Some("measure: click another vertex".to_string()),
+            (true, [a, b, ..]) => {
+                let delta = [b.x - a.x, b.y - a.y, b.z - a.z];
+                let distance = (delta[0].powi(2) + delta[1].powi(2) + delta[2].powi(2)).sqrt();
+                let [dx, dy, dz] = delta.map(|length| overlay::format_length(length, 4));
+                Some(format!("distance: {} (Δx {}, Δy {}, Δz {})", overlay::format_length(distance, 4), dx, dy, dz))
+            }
+        };
+
+        // Prefix the latest notice, measurements and sizes to every variant, and the diff summary, when diffing, to the longer ones.
+        let notice_prefix: String = notice.iter().chain(measure_msg.iter()).chain(size_msg.iter())
+            .map(|msg| format!("{} | ", msg))
+            .collect();
+        let diff_prefix = match &diff_msg {
+            Some(diff_msg) => format!("{}{} | ", notice_prefix, diff_msg),
+            None => notice_prefix.clone()
+        };
+
         let msgs = (
-            format!("{} | {} | {} | {}", points_mode_msg, braile_mode_msg, resolution_msg, fps_msg),
-            format!("{} | {} | {}", points_mode_msg, braile_mode_msg, resolution_msg),
-            format!("{} | {}", points_mode_msg, braile_mode_msg),
-            points_mode_msg.to_string(),
+            format!(
+                "{}{} | {} | {} | {}{} | {} | {}", 
+                diff_prefix, points_mode_msg, braile_mode_msg, projection_msg, fov_msg, grid_msg, resolution_msg, frame_time_msg
+            ),
+            format!(
+                "{}{} | {} | {} | {}{} | {}", 
+                diff_prefix, points_mode_msg, braile_mode_msg, projection_msg, fov_msg, grid_msg, resolution_msg
+            ),
+            format!("{}{} | {}", notice_prefix, points_mode_msg, braile_mode_msg),
+            format!("{}{}", notice_prefix, points_mode_msg),
         );
 
-        let final_msg = match terminal::size().unwrap().0 as usize {
+        let (terminal_width, terminal_height) = terminal::size().unwrap();
+        let final_msg = match terminal_width as usize {
             width if width > msgs.0.len() => { msgs.0 }
             width if width > msgs.1.len() => { msgs.1 }
             width if width > msgs.2.len() => { msgs.2 }
             width if width > msgs.3.len() => { msgs.3 }
-            _ => { "".to_string() }
+
+            // Cut the notice and measurements short rather than lose them.
+            width => { notice_prefix.trim_end_matches(" | ").chars().take(width.saturating_sub(1)).collect() }
         };
 
-        execute!(
-            io::stdout(),
-            terminal::Clear(terminal::ClearType::CurrentLine),
-            style::Print(final_msg),
-        ).unwrap();
+        if final_msg != last_msg {
+            queue!(
+                frame,
+                cursor::MoveTo(0, terminal_height.saturating_sub(1)),
+                terminal::Clear(terminal::ClearType::CurrentLine),
+                style::Print(&final_msg),
+            ).unwrap();
+            last_msg = final_msg;
+        }
+
+        let write_start = time::Instant::now();
+        let mut stdout = io::stdout();
+        stdout.write_all(&frame).unwrap();
+        stdout.flush().unwrap();
+
+        // Pace the following frames to the link.
+        if args.remote { frame_limiter.record(frame.len(), write_start.elapsed()) }
     }
 }
@@ -1,5 +1,20 @@
-use crate::three;
+use crate::{bvh, three};
 use std::*;
+use collections::HashMap;
+use crossterm::style;
+
+// Number of grid cells across the largest side of the model, for each level of detail, finest first.
+const LOD_RESOLUTIONS: [f32; 4] = [256., 128., 64., 32.];
+
+// A level of detail only replaces the previous one if it has at most this fraction of its edges.
+const LOD_MIN_REDUCTION: f32 = 0.7;
+
+// A simplified version of a model, in model space.
+#[derive(Clone)]
+pub struct Lod {
+    pub points: Vec<three::Point>,
+    pub edges: Vec<(three::Point, three::Point)>
+}
 
 // Error for .obj parsing failures.
 #[derive(Debug)]
@@ -22,34 +37,47 @@ impl error::Error for ObjParseError {
 }
 
 // Simple 3d point wrapper.
+#[derive(Clone)]
 pub struct Model {
     // Defined in model space.
     pub points: Vec<three::Point>,
     pub edges: Vec<(three::Point, three::Point)>,
 
-    // Position of (0, 0, 0) in model space, in world space.
-    pub position: three::Point
+    // Placement of the model in world space.
+    pub transform: three::Transform,
+
+    // Color to draw the model in.
+    pub color: style::Color,
+
+    // Simplified versions of the model, from finest to coarsest.
+    pub lods: Vec<Lod>,
+
+    // Hierarchy of boxes around the edges, empty until built.
+    pub bvh: bvh::Bvh
 }
 
 #[allow(dead_code)]
 impl Model {
-    // Creates a new model at a specified position.
+    // Creates a new model with a specified transform.
     pub fn new(
         points: Vec<three::Point>,
         edges: Vec<(three::Point, three::Point)>,
-        position: three::Point
+        transform: three::Transform
     ) -> Model {
         Model{
             points,
-            position,
             edges,
+            transform,
+            color: style::Color::Reset,
+            lods: Vec::new(),
+            bvh: bvh::Bvh::new(),
         }
     }
 
-    // Creates a new cube, centered at the specified position, with the specified side-length.
+    // Creates a new cube, centered at the model origin, with the specified side-length.
     pub fn new_cube(
         side_length: f32,
-        position: three::Point
+        transform: three::Transform
     ) -> Model {
         let front = (
             three::Point::new(-side_length/2., -side_length/2., side_length/2.),
@@ -83,12 +111,15 @@ impl Model {
                 (rear.2, front.2),
                 (rear.3, front.3),
             ],
-            position,
+            transform,
+            color: style::Color::Reset,
+            lods: Vec::new(),
+            bvh: bvh::Bvh::new(),
         }
     }
 
     // Creates a model from a .obj file.
-    pub fn new_obj(path: &str, position: three::Point) -> Result<Model, Box<dyn error::Error>> {
+    pub fn new_obj(path: &str, transform: three::Transform) -> Result<Model, Box<dyn error::Error>> {
 
         // Read the file.
         let mut code = fs::read_to_string(path)?;
@@ -220,36 +251,155 @@ impl Model {
                 (vertices[start_index], vertices[end_index])
         ).collect();
 
-        Ok(Model{
+        let mut model = Model{
             points: vertices,
             edges,
-            position,
-        })
+            transform,
+            color: style::Color::Reset,
+            lods: Vec::new(),
+            bvh: bvh::Bvh::new(),
+        };
+        model.build_lods();
+        model.build_bvh();
+
+        Ok(model)
     }
 
-    pub fn model_to_world(&self, point: &three::Point) -> three::Point {
-        three::Point{
-            x: point.x + self.position.x,
-            y: point.y + self.position.y,
-            z: point.z + self.position.z
+    // Computes the levels of detail, by clustering vertices on successively coarser grids.
+    // Every vertex moves to the average of its cell, and edges within a single cell disappear.
+    pub fn build_lods(&mut self) {
+        self.lods.clear();
+
+        // Find the largest side of the model in model space.
+        let mut points = self.edges.iter()
+            .flat_map(|edge| iter::once(&edge.0).chain(iter::once(&edge.1)))
+            .chain(self.points.iter());
+        let Some(first) = points.next() else { return };
+
+        let (mut min, mut max) = (*first, *first);
+        for point in points {
+            min = three::Point::new(min.x.min(point.x), min.y.min(point.y), min.z.min(point.z));
+            max = three::Point::new(max.x.max(point.x), max.y.max(point.y), max.z.max(point.z));
+        }
+        let size = (max.x - min.x).max(max.y - min.y).max(max.z - min.z);
+        if size == 0. { return }
+
+        let mut previous_edge_count = self.edges.len();
+        for resolution in LOD_RESOLUTIONS {
+            let cell_size = size / resolution;
+            let cell = |point: &three::Point| (
+                ((point.x - min.x) / cell_size) as i64,
+                ((point.y - min.y) / cell_size) as i64,
+                ((point.z - min.z) / cell_size) as i64
+            );
+
+            // Average every cell's vertices, including edge endpoints.
+            let mut sums = HashMap::<(i64, i64, i64), (three::Point, f32)>::new();
+            let endpoints = self.edges.iter().flat_map(|edge| iter::once(&edge.0).chain(iter::once(&edge.1)));
+            for point in self.points.iter().chain(endpoints) {
+                let (sum, count) = sums.entry(cell(point)).or_insert((three::Point::new(0., 0., 0.), 0.));
+                *sum = three::Point::new(sum.x + point.x, sum.y + point.y, sum.z + point.z);
+                *count += 1.;
+            }
+            let average = |cell: &(i64, i64, i64)| {
+                let (sum, count) = sums[cell];
+                three::Point::new(sum.x / count, sum.y / count, sum.z / count)
+            };
+
+            // Connect cells that had an edge between them, once.
+            let mut cell_edges: Vec<_> = self.edges.iter()
+                .map(|edge| {
+                    let (start, end) = (cell(&edge.0), cell(&edge.1));
+                    if start < end { (start, end) } else { (end, start) }
+                })
+                .filter(|(start, end)| start != end)
+                .collect();
+            cell_edges.sort();
+            cell_edges.dedup();
+
+            // Skip levels that barely simplify the model.
+            if cell_edges.len() as f32 > previous_edge_count as f32 * LOD_MIN_REDUCTION { continue }
+            previous_edge_count = cell_edges.len();
+
+            let mut cell_points: Vec<_> = self.points.iter().map(cell).collect();
+            cell_points.sort();
+            cell_points.dedup();
+
+            self.lods.push(Lod {
+                points: cell_points.iter().map(average).collect(),
+                edges: cell_edges.iter().map(|(start, end)| (average(start), average(end))).collect()
+            });
         }
     }
 
-    // Returns the min and max bounds of the model in model space (rectangular prism).
-    pub fn world_bounds(&self) -> (three::Point, three::Point) {
-        if self.points.is_empty() && self.edges.is_empty() {
-            return (
-                three::Point::new(0., 0., 0.), 
-                three::Point::new(0., 0., 0.)
-            )
+    // Builds the hierarchy of boxes around the edges, reordering them.
+    pub fn build_bvh(&mut self) {
+        self.bvh = bvh::Bvh::build(&mut self.edges);
+    }
+
+    // Finds the point of the model's edges nearest to the origin of a ray in world space,
+    // that is within tolerance times its distance from it. Needs the hierarchy to be built.
+    pub fn pick(&self, origin: &three::Point, direction: &three::Point, tolerance: f32) -> Option<three::Point> {
+        let model_origin = self.world_to_model(origin);
+        let model_end = self.world_to_model(&three::Point::new(
+            origin.x + direction.x, 
+            origin.y + direction.y, 
+            origin.z + direction.z
+        ));
+        let model_direction = three::Point::new(
+            model_end.x - model_origin.x, 
+            model_end.y - model_origin.y, 
+            model_end.z - model_origin.z
+        );
+
+        self.bvh.pick(&self.edges, &model_origin, &model_direction, tolerance)
+            .map(|point| self.model_to_world(&point))
+    }
+
+    // The finest level of detail with at most max_edges edges, or the coarsest if none are small enough.
+    // Returns the full model if it is small enough itself.
+    pub fn lod(&self, max_edges: usize) -> (&[three::Point], &[(three::Point, three::Point)]) {
+        if self.edges.len() <= max_edges { return (&self.points, &self.edges) }
+
+        match self.lods.iter().find(|lod| lod.edges.len() <= max_edges).or(self.lods.last()) {
+            Some(lod) => (&lod.points, &lod.edges),
+            None => (&self.points, &self.edges)
         }
+    }
 
-        let mut min_bounds = self.points[0];
-        let mut max_bounds = self.points[0];
+    // Whether the model has no geometry at all.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty() && self.edges.is_empty()
+    }
+
+    // Rotates the model around its origin, after any rotation already applied.
+    pub fn rotate(&mut self, rotation: &three::Quaternion) {
+        self.transform.rotation = rotation.mul(&self.transform.rotation).normalize();
+    }
 
-        let points_including_edges = self.edges.iter()
+    // Convert model to world coordinates.
+    pub fn model_to_world(&self, point: &three::Point) -> three::Point {
+        self.transform.apply(point)
+    }
+
+    // Convert world to model coordinates.
+    pub fn world_to_model(&self, point: &three::Point) -> three::Point {
+        self.transform.apply_inverse(point)
+    }
+
+    // Returns the min and max bounds of the model in world space (rectangular prism).
+    pub fn world_bounds(&self) -> (three::Point, three::Point) {
+        let mut points_including_edges = self.edges.iter()
             .flat_map(|tup| iter::once(&tup.0).chain(iter::once(&tup.1)))
-            .chain(self.points.iter());
+            .chain(self.points.iter())
+            .map(|point| self.model_to_world(point));
+
+        let Some(first) = points_including_edges.next() else {
+            return (self.transform.translation, self.transform.translation)
+        };
+
+        let mut min_bounds = first;
+        let mut max_bounds = first;
 
         for point in points_including_edges {
             min_bounds.x = f32::min(point.x, min_bounds.x);
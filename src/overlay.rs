@@ -0,0 +1,111 @@
+use crate::three;
+use std::*;
+use crossterm::style;
+
+// Colors of the x, y and z axes, and of the ground grid.
+const AXIS_COLORS: [style::Color; 3] = [style::Color::Red, style::Color::Green, style::Color::Blue];
+const GRID_COLOR: style::Color = style::Color::DarkGrey;
+const MEASURE_COLOR: style::Color = style::Color::Yellow;
+
+// Distance from a picked vertex to the edges of its marker, in subpixels.
+const MARKER_SIZE: i32 = 2;
+
+// Length of the axes, and the distance of their origin from the bottom left corner of the screen, in subpixels.
+const AXIS_LENGTH: f32 = 20.;
+const AXIS_MARGIN: f32 = 28.;
+
+// About how many grid cells span the diagonal of the scene.
+const GRID_CELLS: f32 = 10.;
+
+// Format a length, rounded to about the given number of significant digits.
+pub fn format_length(length: f32, digits: i32) -> String {
+    let magnitude = if length == 0. { 0 } else { length.abs().log10().floor() as i32 };
+    format!("{:.*}", (digits - 1 - magnitude).max(0) as usize, length)
+}
+
+// Draw the world axes in the bottom left corner of the screen,
+// as if they were at the given depth in front of the camera, so they turn along with the scene.
+pub fn draw_axes(camera: &mut three::Camera, depth: f32) {
+    // Draw them without perspective, as it would stretch them so far from the center of the view.
+    let width = camera.subpixel_width(depth) * camera.screen.width as f32;
+    let projection = camera.orthographic_width.replace(width);
+
+    let (origin, direction) = camera.ray(AXIS_MARGIN, camera.screen.height as f32 - AXIS_MARGIN);
+    let start = three::Point::new(
+        origin.x + direction.x * depth,
+        origin.y + direction.y * depth,
+        origin.z + direction.z * depth
+    );
+    let length = AXIS_LENGTH * camera.subpixel_width(depth);
+
+    for (axis, color) in [three::Axis::X, three::Axis::Y, three::Axis::Z].iter().zip(AXIS_COLORS) {
+        let unit = axis.unit();
+        camera.screen.set_color(color);
+        camera.edge(&start, &three::Point::new(
+            start.x + unit.x * length,
+            start.y + unit.y * length,
+            start.z + unit.z * length
+        ));
+    }
+
+    camera.orthographic_width = projection;
+}
+
+// Spacing of a grid for bounds, given as (min, max), a round number near a fraction of their diagonal.
+pub fn grid_spacing(bounds: &(three::Point, three::Point)) -> f32 {
+    let target = three::bounds_diagonal(bounds) / GRID_CELLS;
+    if target <= 0. { return 1. }
+
+    let magnitude = 10f32.powf(target.log10().floor());
+    [1., 2., 5.].into_iter()
+        .map(|step| step * magnitude)
+        .find(|spacing| *spacing >= target)
+        .unwrap_or(10. * magnitude)
+}
+
+// Draw a grid on the ground under bounds, given as (min, max), at their lowest y,
+// with lines on multiples of the spacing, reaching at least one cell past them on every side.
+pub fn draw_grid(camera: &mut three::Camera, bounds: &(three::Point, three::Point)) {
+    let spacing = grid_spacing(bounds);
+    let (min, max) = bounds;
+    let (start_x, end_x) = ((min.x / spacing).floor() as i32 - 1, (max.x / spacing).ceil() as i32 + 1);
+    let (start_z, end_z) = ((min.z / spacing).floor() as i32 - 1, (max.z / spacing).ceil() as i32 + 1);
+    let point = |x: i32, z: i32| three::Point::new(x as f32 * spacing, min.y, z as f32 * spacing);
+
+    camera.screen.set_color(GRID_COLOR);
+    for x in start_x..=end_x {
+        camera.edge(&point(x, start_z), &point(x, end_z));
+    }
+    for z in start_z..=end_z {
+        camera.edge(&point(start_x, z), &point(end_x, z));
+    }
+}
+
+// Draw the edges of an axis aligned box, given as (min, max).
+pub fn draw_box(camera: &mut three::Camera, bounds: &(three::Point, three::Point), color: style::Color) {
+    let (min, max) = bounds;
+    let corner = |x: bool, y: bool, z: bool| three::Point::new(
+        if x { max.x } else { min.x },
+        if y { max.y } else { min.y },
+        if z { max.z } else { min.z }
+    );
+
+    // Every edge joins a corner to one differing along a single axis.
+    camera.screen.set_color(color);
+    for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+        camera.edge(&corner(false, a, b), &corner(true, a, b));
+        camera.edge(&corner(a, false, b), &corner(a, true, b));
+        camera.edge(&corner(a, b, false), &corner(a, b, true));
+    }
+}
+
+// Mark picked points, and join them with a line.
+pub fn draw_measurement(camera: &mut three::Camera, picks: &[three::Point]) {
+    camera.screen.set_color(MEASURE_COLOR);
+    for pick in picks.iter() {
+        camera.marker(pick, MARKER_SIZE);
+    }
+    for pair in picks.windows(2) {
+        camera.edge(&pair[0], &pair[1]);
+    }
+}
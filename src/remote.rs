@@ -0,0 +1,64 @@
+use std::*;
+use time::Duration;
+
+// Weight of the newest measurement in the throughput average.
+const THROUGHPUT_SMOOTHING: f32 = 0.3;
+
+// Slowest the frame rate is allowed to drop to.
+const MAX_FRAME_DURATION: Duration = Duration::from_millis(1000);
+
+// Writes faster than this are buffered by the OS, and say nothing about the link.
+const MIN_MEASURED_WRITE_DURATION: Duration = Duration::from_millis(2);
+
+// Paces frames to the rate at which the output absorbs them, for slow links.
+// Writes to a terminal block once its buffers are full, so over a slow link
+// the time a write takes tracks the bandwidth of the link.
+pub struct FrameLimiter {
+    // Frame duration to aim for when the link keeps up.
+    target_frame_duration: Duration,
+
+    // Moving average of measured throughput in bytes per second, None until measured.
+    throughput: Option<f32>,
+
+    // Duration to leave between the start of the last frame and the next one.
+    frame_duration: Duration
+}
+
+impl FrameLimiter {
+    // Create a new limiter aiming for the given frame duration.
+    pub fn new(target_frame_duration: Duration) -> FrameLimiter {
+        FrameLimiter {
+            target_frame_duration,
+            throughput: None,
+            frame_duration: target_frame_duration
+        }
+    }
+
+    // Record that a frame of the given size took the given time to write.
+    pub fn record(&mut self, bytes: usize, write_duration: Duration) {
+        if write_duration >= MIN_MEASURED_WRITE_DURATION {
+            let measured = bytes as f32 / write_duration.as_secs_f32();
+            self.throughput = Some(match self.throughput {
+                Some(throughput) => throughput + THROUGHPUT_SMOOTHING * (measured - throughput),
+                None => measured
+            });
+        }
+
+        // Leave enough time for a frame of the same size to drain before sending the next.
+        self.frame_duration = match self.throughput {
+            Some(throughput) => Duration::from_secs_f32(bytes as f32 / throughput)
+                .clamp(self.target_frame_duration, MAX_FRAME_DURATION),
+            None => self.target_frame_duration
+        };
+    }
+
+    // Duration to leave between the start of the last frame and the next one.
+    pub fn frame_duration(&self) -> Duration {
+        self.frame_duration
+    }
+
+    // Measured throughput in bytes per second, if the link has been slow enough to measure.
+    pub fn throughput(&self) -> Option<f32> {
+        self.throughput
+    }
+}
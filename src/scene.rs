@@ -0,0 +1,130 @@
+use crate::{model, three};
+use std::*;
+use crossterm::style;
+
+// Colors given to models, in the order they are loaded.
+const PALETTE: [style::Color; 6] = [
+    style::Color::Cyan,
+    style::Color::Yellow,
+    style::Color::Magenta,
+    style::Color::Green,
+    style::Color::Red,
+    style::Color::Blue,
+];
+
+// Space between models in a grid, relative to the largest model's diagonal.
+const GRID_SPACING_MULTIPLIER: f32 = 1.2;
+
+// How models are placed relative to each other.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Layout {
+    // Keep every model in its own coordinates, drawn on top of each other.
+    Overlay,
+
+    // Center every model in its own cell of a grid, in reading order.
+    // A single model keeps its own coordinates.
+    Grid
+}
+
+// A collection of models viewed together.
+pub struct Scene {
+    pub models: Vec<model::Model>,
+    pub layout: Layout
+}
+
+impl Scene {
+    // Creates a scene from the given models.
+    // If there is more than one, models without a color of their own are colored from the palette.
+    pub fn new(mut models: Vec<model::Model>, layout: Layout) -> Scene {
+        if models.len() > 1 {
+            let uncolored = models.iter_mut().filter(|model| model.color == style::Color::Reset);
+            for (model, color) in uncolored.zip(PALETTE.iter().cycle()) {
+                model.color = *color;
+            }
+        }
+
+        let mut scene = Scene { models, layout };
+        scene.arrange();
+        scene
+    }
+
+    // Position the models according to the layout.
+    pub fn arrange(&mut self) {
+        // Start from the models' own coordinates.
+        for model in self.models.iter_mut() {
+            model.transform.translation = three::Point::new(0., 0., 0.);
+        }
+
+        if self.layout == Layout::Overlay || self.models.len() < 2 { return }
+
+        let cell_size = self.models.iter()
+            .map(|model| three::bounds_diagonal(&model.world_bounds()))
+            .fold(0., f32::max) * GRID_SPACING_MULTIPLIER;
+        let columns = (self.models.len() as f32).sqrt().ceil() as usize;
+
+        // Move the center of every model to the center of its cell.
+        for (index, model) in self.models.iter_mut().enumerate() {
+            let center = three::bounds_center(&model.world_bounds());
+            let (column, row) = (index % columns, index / columns);
+            model.transform.translation = three::Point::new(
+                column as f32 * cell_size - center.x,
+                -(row as f32) * cell_size - center.y,
+                -center.z
+            );
+        }
+    }
+
+    // Rotates every model around its origin, and rearranges them.
+    pub fn rotate(&mut self, rotation: &three::Quaternion) {
+        for model in self.models.iter_mut() {
+            model.rotate(rotation);
+        }
+        self.arrange();
+    }
+
+    // Returns the min and max bounds of all models in world space, ignoring empty models.
+    pub fn world_bounds(&self) -> (three::Point, three::Point) {
+        self.models.iter()
+            .filter(|model| !model.is_empty())
+            .map(|model| model.world_bounds())
+            .reduce(union)
+            .unwrap_or((three::Point::new(0., 0., 0.), three::Point::new(0., 0., 0.)))
+    }
+
+    // Returns the min and max bounds in world space of what the camera sees of all models, if anything.
+    pub fn visible_bounds(&self, camera: &three::Camera) -> Option<(three::Point, three::Point)> {
+        self.models.iter()
+            .filter_map(|model| camera.visible_bounds(model))
+            .reduce(union)
+    }
+
+    // Finds the point of any model's edges nearest to the origin of a ray in world space,
+    // that is within tolerance times its distance from it.
+    pub fn pick(&self, origin: &three::Point, direction: &three::Point, tolerance: f32) -> Option<three::Point> {
+        let distance = |point: &three::Point| 
+            (point.x - origin.x).powi(2) + (point.y - origin.y).powi(2) + (point.z - origin.z).powi(2);
+
+        self.models.iter()
+            .filter_map(|model| model.pick(origin, direction, tolerance))
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+}
+
+// Smallest bounds containing two bounds, each given as (min, max).
+fn union(
+    (min, max): (three::Point, three::Point), 
+    (other_min, other_max): (three::Point, three::Point)
+) -> (three::Point, three::Point) {
+    (
+        three::Point::new(
+            f32::min(min.x, other_min.x), 
+            f32::min(min.y, other_min.y), 
+            f32::min(min.z, other_min.z)
+        ),
+        three::Point::new(
+            f32::max(max.x, other_max.x), 
+            f32::max(max.y, other_max.y), 
+            f32::max(max.z, other_max.z)
+        ),
+    )
+}
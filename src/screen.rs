@@ -1,6 +1,6 @@
 use std::*;
 use crossterm::{
-    execute, 
+    queue,
     terminal,
     cursor,
     style
@@ -8,6 +8,12 @@ use crossterm::{
 
 const DEFAULT_TERMINAL_DIMENSIONS: (u16, u16) = (80, 24);
 
+// Longest stretch of unchanged cells reprinted to avoid moving the cursor.
+const MAX_REPRINTED_GAP: usize = 2;
+
+// A character on the terminal, and its color (None if it is empty).
+type Glyph = (char, Option<style::Color>);
+
 // Setup ability to get dimensions out of matrix arrays.
 pub trait Dim {
     const WIDTH: usize;
@@ -88,27 +94,40 @@ impl Point {
 }
 
 // Wrapper for a "screen" to render.
+// Each subpixel holds the pen it was drawn with, or 0 if it is empty.
+// Pens are numbered in the order their colors were first used this frame,
+// and when a pixel mixes pens, the latest one decides its color.
 pub struct Screen {
     pub width: u16,
     pub height: u16,
-    content: Vec<Vec<bool>>,
+
+    // Whether to render colors, or everything in the default foreground.
+    pub color_mode: bool,
+
+    // Color of every pen, pen n is at index n - 1.
+    pens: Vec<style::Color>,
+
+    // Pen used by subsequent writes.
+    pen: u16,
+
+    content: Vec<Vec<u16>>,
+
+    // Glyphs currently on the terminal, to only redraw what changed.
+    previous_frame: Vec<Vec<Glyph>>,
 }
 
 impl Screen {
-    // Create a new screen, sized to the terminal.
+    // Create a new, empty screen.
+    // The terminal is cleared on the first render.
     pub fn new() -> Screen {
-        // Clear term and go to 0, 0.
-        execute!(
-            io::stdout(),
-            cursor::MoveTo(0, 0),
-            terminal::Clear(terminal::ClearType::All)
-        ).unwrap();
-
-        // Create screen.
         Screen{
             content: Vec::new(),
             width: 0,
-            height: 0
+            height: 0,
+            color_mode: true,
+            pens: vec![style::Color::Reset],
+            pen: 1,
+            previous_frame: Vec::new()
         }
     }
 
@@ -131,13 +150,55 @@ impl Screen {
         let x_in_bounds = 0 < point.x && point.x < self.width as i32;
         let y_in_bounds = 0 < point.y && point.y < self.height as i32;
         if x_in_bounds && y_in_bounds {
-            self.content[point.y as usize][point.x as usize] = val;
+            self.content[point.y as usize][point.x as usize] = if val { self.pen } else { 0 };
         }
     }
 
-    // Clears the whole screen.
+    // Create an empty screen of the same size, drawing with the current pen, to be merged back later.
+    pub fn new_layer(&self) -> Screen {
+        Screen {
+            width: self.width,
+            height: self.height,
+            color_mode: self.color_mode,
+            pens: self.pens.clone(),
+            pen: self.pen,
+            content: vec![vec![0; self.width as usize]; self.height as usize],
+            previous_frame: Vec::new()
+        }
+    }
+
+    // Draw everything drawn on a layer over this screen.
+    pub fn merge(&mut self, layer: &Screen) {
+        for (row, layer_row) in self.content.iter_mut().zip(layer.content.iter()) {
+            for (subpixel, layer_subpixel) in row.iter_mut().zip(layer_row.iter()) {
+                if *layer_subpixel != 0 { *subpixel = *layer_subpixel }
+            }
+        }
+    }
+
+    // Use the given color for subsequent writes.
+    // Every change of color takes a new, higher pen, so that cells take the color drawn last.
+    pub fn set_color(&mut self, color: style::Color) {
+        if self.pens[self.pen as usize - 1] == color { return }
+
+        // Out of pens, fall back to reusing one of the same color.
+        if self.pens.len() == u16::MAX as usize {
+            if let Some(index) = self.pens.iter().position(|&pen_color| pen_color == color) {
+                self.pen = index as u16 + 1;
+                return
+            }
+            self.pens.pop();
+        }
+
+        self.pens.push(color);
+        self.pen = self.pens.len() as u16;
+    }
+
+    // Clears the whole screen, and resets the pens.
     pub fn clear(&mut self) {
-        self.content = vec![vec![false; self.width as usize]; self.height as usize];
+        self.content = vec![vec![0; self.width as usize]; self.height as usize];
+        self.pens = vec![style::Color::Reset];
+        self.pen = 1;
     }
 
     // Resizes the screen.
@@ -147,7 +208,7 @@ impl Screen {
         // Handle height.
         if height > self.height {
             self.content.extend(vec![
-                vec![false; width as usize]; 
+                vec![0; width as usize]; 
                 (height - self.height) as usize
             ])
         } else {
@@ -158,7 +219,7 @@ impl Screen {
         // Handle width.
         if width > self.width {
             for row in self.content.iter_mut() {
-                row.extend(vec![false; (width - self.width) as usize]);
+                row.extend(vec![0; (width - self.width) as usize]);
             }
         } else {
             for row in self.content.iter_mut() {
@@ -168,6 +229,56 @@ impl Screen {
         self.width = width;
     }
 
+    // Clip a line to the screen with the Cohen-Sutherland algorithm.
+    // See https://en.wikipedia.org/wiki/Cohen%E2%80%93Sutherland_algorithm.
+    // Returns None if the line is entirely off screen.
+    pub fn clip_line(&self, start: (f32, f32), end: (f32, f32)) -> Option<((f32, f32), (f32, f32))> {
+        const LEFT: u8 = 1;
+        const RIGHT: u8 = 2;
+        const TOP: u8 = 4;
+        const BOTTOM: u8 = 8;
+
+        let (max_x, max_y) = (self.width as f32 - 1., self.height as f32 - 1.);
+        let outcode = |(x, y): (f32, f32)| {
+            let mut code = 0;
+            if x < 0. { code |= LEFT } else if x > max_x { code |= RIGHT }
+            if y < 0. { code |= TOP } else if y > max_y { code |= BOTTOM }
+            code
+        };
+
+        let (mut start, mut end) = (start, end);
+        let (mut start_code, mut end_code) = (outcode(start), outcode(end));
+
+        loop {
+            // Both points on screen.
+            if start_code | end_code == 0 { return Some((start, end)) }
+
+            // Both points off the same side of the screen.
+            if start_code & end_code != 0 { return None }
+
+            // Move a point that is off screen to the side it is off.
+            let code = if start_code != 0 { start_code } else { end_code };
+            let (delta_x, delta_y) = (end.0 - start.0, end.1 - start.1);
+            let point = if code & TOP != 0 {
+                (start.0 + delta_x * (0. - start.1) / delta_y, 0.)
+            } else if code & BOTTOM != 0 {
+                (start.0 + delta_x * (max_y - start.1) / delta_y, max_y)
+            } else if code & LEFT != 0 {
+                (0., start.1 + delta_y * (0. - start.0) / delta_x)
+            } else {
+                (max_x, start.1 + delta_y * (max_x - start.0) / delta_x)
+            };
+
+            if code == start_code {
+                start = point;
+                start_code = outcode(start);
+            } else {
+                end = point;
+                end_code = outcode(end);
+            }
+        }
+    }
+
     // Draw a line with Bresenham's line algorithm.
     // See https://en.wikipedia.org/wiki/Bresenham%27s_line_algorithm.
     pub fn line(&mut self, start: &Point, end: &Point) {            
@@ -198,44 +309,221 @@ impl Screen {
         }
     }
 
-    // Render the screen in the given pixel.
-    pub fn render<PixelType: Pixel>(&self) {
-        execute!(
-            io::stdout(),
-            cursor::MoveTo(0, 0)
-        ).unwrap();
+    // Forget what is on the terminal, so that the next render redraws everything.
+    pub fn invalidate(&mut self) {
+        self.previous_frame.clear();
+    }
 
-        // Chunk rows by the height of a single pixel.
-        let chunked_rows = self.content.chunks(PixelType::HEIGHT);
+    // Produce the glyph and color of every terminal cell, for the given pixel.
+    fn glyphs<PixelType: Pixel>(&self) -> Vec<Vec<Glyph>> {
+        let real_row_width = self.width.div_ceil(PixelType::WIDTH as u16) as usize;
 
-        // Run through chunks.
-        for subrows in chunked_rows {
+        // Chunk rows by the height of a single pixel.
+        self.content.chunks(PixelType::HEIGHT).map(|subrows| {
 
-            // Produce a "real row" - a row of Pixel types.
-            let real_row_width = self.width.div_ceil(PixelType::WIDTH as u16) as usize;
+            // Produce a "real row" - a row of Pixel types, and the latest pen of each.
             let mut real_row = vec![PixelType::new(); real_row_width];
+            let mut real_row_pens = vec![0; real_row_width];
 
             // Run through every subrow, where subpixel_y is the y index within the pixel.
             for (subpixel_y, subrow) in subrows.iter().enumerate() {
 
-                // Chunk the subrow by the width of the pixel.
-                let chunked_subrow = subrow.chunks_exact(2);
-                let remainder = chunked_subrow.remainder();
-
-                // Update real row.
-                for (real_x, pixel_row) in chunked_subrow.enumerate() {
-                    real_row[real_x][subpixel_y][..pixel_row.len()].copy_from_slice(pixel_row);
+                // Run through every subpixel, where x is the x index in the subrow.
+                for (x, subpixel) in subrow.iter().enumerate() {
+                    let real_x = x / PixelType::WIDTH;
+                    real_row[real_x][subpixel_y][x % PixelType::WIDTH] = *subpixel != 0;
+                    real_row_pens[real_x] = real_row_pens[real_x].max(*subpixel);
                 }
-                
-                // Handle remainder (indivisible width).
-                real_row[real_row_width - 1][subpixel_y][..remainder.len()].copy_from_slice(remainder);
             }
 
-            // Render.
-            for pixel in real_row {
-                execute!(io::stdout(), style::Print(pixel.to_char())).unwrap();
+            // Empty pixels have no color, so that recoloring them is never seen as a change.
+            real_row.into_iter().zip(real_row_pens).map(|(pixel, pen)| {
+                let color = match pen {
+                    0 => None,
+                    _ if self.color_mode => Some(self.pens[pen as usize - 1]),
+                    _ => Some(style::Color::Reset)
+                };
+                (pixel.to_char(), color)
+            }).collect()
+        }).collect()
+    }
+
+    // Render the screen in the given pixel, queueing only the cells that changed since the last render.
+    // Nothing is flushed, so that the caller can send the whole frame at once.
+    pub fn render<PixelType: Pixel>(&mut self, out: &mut impl io::Write) -> io::Result<()> {
+        let frame = self.glyphs::<PixelType>();
+
+        // Redraw from scratch if the size changed, as the terminal may have reflowed.
+        let same_size = self.previous_frame.len() == frame.len() && 
+            self.previous_frame.iter().zip(frame.iter()).all(|(previous, row)| previous.len() == row.len());
+
+        if !same_size {
+            queue!(out, terminal::Clear(terminal::ClearType::All))?;
+            self.previous_frame.clear();
+        }
+
+        // Color the terminal is currently printing with, unknown to begin with.
+        let mut current_color = None;
+
+        for (y, row) in frame.iter().enumerate() {
+            let previous_row = self.previous_frame.get(y);
+            let changed = |x: usize| previous_row.is_none_or(|previous_row| previous_row[x] != row[x]);
+
+            let mut x = 0;
+            while x < row.len() {
+                if !changed(x) { 
+                    x += 1;
+                    continue
+                }
+
+                // Start a run of changed cells.
+                queue!(out, cursor::MoveTo(x as u16, y as u16))?;
+                let mut run = String::new();
+
+                while x < row.len() {
+                    // Continue through short stretches of unchanged cells, 
+                    // as reprinting them is cheaper than moving the cursor.
+                    if !changed(x) {
+                        let gap = (x..row.len().min(x + MAX_REPRINTED_GAP + 1)).take_while(|&x| !changed(x)).count();
+                        if gap > MAX_REPRINTED_GAP || x + gap == row.len() { break }
+                    }
+
+                    let (glyph, color) = row[x];
+                    if let Some(new_color) = color.filter(|_| color != current_color) {
+                        queue!(out, style::Print(&run), style::SetForegroundColor(new_color))?;
+                        run.clear();
+                        current_color = color;
+                    }
+
+                    run.push(glyph);
+                    x += 1;
+                }
+
+                queue!(out, style::Print(&run))?;
             }
-            execute!(io::stdout(), style::Print("\r\n")).unwrap();
         }
+
+        queue!(out, style::ResetColor)?;
+        self.previous_frame = frame;
+        Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Screen spanning 0 to 99 across, and 0 to 49 down.
+    fn screen() -> Screen {
+        let mut screen = Screen::new();
+        screen.resize(100, 50);
+        screen.clear();
+        screen
+    }
+
+    fn assert_near((a, b): ((f32, f32), (f32, f32)), (c, d): ((f32, f32), (f32, f32))) {
+        for (actual, expected) in [a.0, a.1, b.0, b.1].into_iter().zip([c.0, c.1, d.0, d.1]) {
+            assert!((actual - expected).abs() < 1e-4, "{:?} is not near {:?}", (a, b), (c, d));
+        }
+    }
+
+    #[test]
+    fn glyphs_take_the_color_drawn_last() {
+        let mut screen = screen();
+
+        // A model drawn over the ground, in the color it was drawn with first.
+        screen.set_color(style::Color::DarkGrey);
+        screen.write(true, &Point::new(2, 2));
+        screen.set_color(style::Color::Reset);
+        screen.write(true, &Point::new(2, 2));
+        screen.write(true, &Point::new(3, 3));
+        assert_eq!(screen.glyphs::<BlockPixel>()[1][1], ('▚', Some(style::Color::Reset)));
+
+        // The ground drawn over it again, in its own color.
+        screen.set_color(style::Color::DarkGrey);
+        screen.write(true, &Point::new(3, 3));
+        assert_eq!(screen.glyphs::<BlockPixel>()[1][1], ('▚', Some(style::Color::DarkGrey)));
+    }
+
+    // Bytes queued by the commands.
+    macro_rules! bytes {
+        ($($command:expr),*) => {{
+            let mut out = Vec::new();
+            queue!(out, $($command),*).unwrap();
+            out
+        }};
+    }
+
+    #[test]
+    fn render_skips_unchanged_frames() {
+        let mut screen = screen();
+        let mut out = Vec::new();
+        screen.render::<BlockPixel>(&mut out).unwrap();
+
+        let mut out = Vec::new();
+        screen.render::<BlockPixel>(&mut out).unwrap();
+        assert_eq!(out, bytes!(style::ResetColor));
+    }
+
+    #[test]
+    fn render_writes_only_changed_cells() {
+        let mut screen = screen();
+        screen.render::<BlockPixel>(&mut Vec::new()).unwrap();
+
+        screen.write(true, &Point::new(10, 5));
+        let mut out = Vec::new();
+        screen.render::<BlockPixel>(&mut out).unwrap();
+        assert_eq!(out, bytes!(
+            cursor::MoveTo(5, 2),
+            style::SetForegroundColor(style::Color::Reset),
+            style::Print('▖'),
+            style::ResetColor
+        ));
+    }
+
+    #[test]
+    fn render_clears_when_resized() {
+        let mut screen = screen();
+        let mut out = Vec::new();
+        screen.render::<BlockPixel>(&mut out).unwrap();
+        assert!(out.starts_with(&bytes!(terminal::Clear(terminal::ClearType::All))));
+
+        screen.resize(80, 50);
+        screen.clear();
+        let mut out = Vec::new();
+        screen.render::<BlockPixel>(&mut out).unwrap();
+        assert!(out.starts_with(&bytes!(terminal::Clear(terminal::ClearType::All))));
+    }
+
+    #[test]
+    fn clip_line_keeps_lines_on_screen() {
+        assert_near(screen().clip_line((10., 10.), (90., 40.)).unwrap(), ((10., 10.), (90., 40.)));
+    }
+
+    #[test]
+    fn clip_line_clips_every_side() {
+        let screen = screen();
+        assert_near(screen.clip_line((-10., 20.), (50., 20.)).unwrap(), ((0., 20.), (50., 20.)));
+        assert_near(screen.clip_line((50., 20.), (150., 20.)).unwrap(), ((50., 20.), (99., 20.)));
+        assert_near(screen.clip_line((30., -10.), (30., 30.)).unwrap(), ((30., 0.), (30., 30.)));
+        assert_near(screen.clip_line((30., 30.), (30., 80.)).unwrap(), ((30., 30.), (30., 49.)));
+    }
+
+    #[test]
+    fn clip_line_clips_both_ends() {
+        // Crosses the screen diagonally, from off the top left corner to off the bottom, near the right.
+        assert_near(screen().clip_line((-10., -5.), (110., 55.)).unwrap(), ((0., 0.), (98., 49.)));
+    }
+
+    #[test]
+    fn clip_line_drops_lines_off_screen() {
+        let screen = screen();
+        assert!(screen.clip_line((-10., 10.), (-1., 40.)).is_none());
+        assert!(screen.clip_line((100., 10.), (200., 40.)).is_none());
+        assert!(screen.clip_line((10., -10.), (90., -1.)).is_none());
+        assert!(screen.clip_line((10., 50.), (90., 60.)).is_none());
+
+        // Passes by a corner, off two different sides.
+        assert!(screen.clip_line((-20., 10.), (10., -20.)).is_none());
+    }
+}
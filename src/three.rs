@@ -1,4 +1,8 @@
 use crate::{model, screen};
+use std::*;
+
+// Fewest edges worth splitting across threads.
+const PARALLEL_MIN_EDGES: usize = 20000;
 
 // Simple 3d point wrapper.
 #[derive(Copy, Clone)]
@@ -15,6 +19,188 @@ impl Point {
     }
 }
 
+// Center of an axis aligned bounding box, given as (min, max).
+pub fn bounds_center(bounds: &(Point, Point)) -> Point {
+    Point::new(
+        (bounds.0.x + bounds.1.x) / 2., 
+        (bounds.0.y + bounds.1.y) / 2., 
+        (bounds.0.z + bounds.1.z) / 2., 
+    )
+}
+
+// Length of the diagonal of an axis aligned bounding box, given as (min, max).
+pub fn bounds_diagonal(bounds: &(Point, Point)) -> f32 {
+    (
+        (bounds.0.x - bounds.1.x).powi(2) +
+        (bounds.0.y - bounds.1.y).powi(2) +
+        (bounds.0.z - bounds.1.z).powi(2)
+    ).sqrt()
+}
+
+// One of the three world axes.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Axis {
+    X,
+    Y,
+    Z
+}
+
+impl Axis {
+    // Unit vector pointing along the axis.
+    pub fn unit(&self) -> Point {
+        match self {
+            Axis::X => Point::new(1., 0., 0.),
+            Axis::Y => Point::new(0., 1., 0.),
+            Axis::Z => Point::new(0., 0., 1.)
+        }
+    }
+}
+
+// Unit quaternion representing a rotation.
+#[derive(Copy, Clone)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32
+}
+
+impl Quaternion {
+    // Create a new quaternion.
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Quaternion {
+        Quaternion { w, x, y, z }
+    }
+
+    // The rotation that does nothing.
+    pub fn identity() -> Quaternion {
+        Quaternion::new(1., 0., 0., 0.)
+    }
+
+    // Rotation of angle radians around the given axis (right hand rule).
+    pub fn from_axis_angle(axis: &Point, angle: f32) -> Quaternion {
+        let length = (axis.x.powi(2) + axis.y.powi(2) + axis.z.powi(2)).sqrt();
+        if length == 0. { return Quaternion::identity() }
+
+        let (s, c) = (angle / 2.).sin_cos();
+        Quaternion::new(c, axis.x / length * s, axis.y / length * s, axis.z / length * s)
+    }
+
+    // Shortest rotation turning the direction of one vector into the direction of another.
+    pub fn between(from: &Point, to: &Point) -> Quaternion {
+        let axis = Point::new(
+            from.y * to.z - from.z * to.y,
+            from.z * to.x - from.x * to.z,
+            from.x * to.y - from.y * to.x
+        );
+        let lengths = (
+            (from.x.powi(2) + from.y.powi(2) + from.z.powi(2)) * (to.x.powi(2) + to.y.powi(2) + to.z.powi(2))
+        ).sqrt();
+        if lengths == 0. { return Quaternion::identity() }
+
+        let cos = ((from.x * to.x + from.y * to.y + from.z * to.z) / lengths).clamp(-1., 1.);
+        Quaternion::from_axis_angle(&axis, cos.acos())
+    }
+
+    // Axis of the rotation, scaled by its angle in radians, turning the short way around.
+    pub fn rotation_vector(&self) -> Point {
+        let rotation = if self.w < 0. { Quaternion::new(-self.w, -self.x, -self.y, -self.z) } else { *self };
+        let sin = (rotation.x.powi(2) + rotation.y.powi(2) + rotation.z.powi(2)).sqrt();
+        if sin == 0. { return Point::new(0., 0., 0.) }
+
+        let angle = 2. * sin.atan2(rotation.w);
+        Point::new(rotation.x / sin * angle, rotation.y / sin * angle, rotation.z / sin * angle)
+    }
+
+    // Hamilton product, the resulting rotation applies other first, then self.
+    pub fn mul(&self, other: &Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        )
+    }
+
+    // The inverse rotation.
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    // Rescale to unit length, to counter floating point drift.
+    pub fn normalize(&self) -> Quaternion {
+        let length = (self.w.powi(2) + self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt();
+        if length == 0. { return Quaternion::identity() }
+        Quaternion::new(self.w / length, self.x / length, self.y / length, self.z / length)
+    }
+
+    // Rotate a point around the origin.
+    pub fn rotate(&self, point: &Point) -> Point {
+        // t = 2 * (q.xyz x p)
+        let t_x = 2. * (self.y * point.z - self.z * point.y);
+        let t_y = 2. * (self.z * point.x - self.x * point.z);
+        let t_z = 2. * (self.x * point.y - self.y * point.x);
+
+        // p' = p + w * t + q.xyz x t
+        Point::new(
+            point.x + self.w * t_x + (self.y * t_z - self.z * t_y),
+            point.y + self.w * t_y + (self.z * t_x - self.x * t_z),
+            point.z + self.w * t_z + (self.x * t_y - self.y * t_x),
+        )
+    }
+}
+
+// Affine transform, applied as scale, then rotation, then translation.
+// Negative scale components mirror along that axis.
+#[derive(Copy, Clone)]
+pub struct Transform {
+    pub translation: Point,
+    pub rotation: Quaternion,
+    pub scale: Point
+}
+
+impl Transform {
+    // Create a new transform.
+    pub fn new(translation: Point, rotation: Quaternion, scale: Point) -> Transform {
+        Transform { translation, rotation, scale }
+    }
+
+    // The transform that does nothing.
+    pub fn identity() -> Transform {
+        Transform::new(Point::new(0., 0., 0.), Quaternion::identity(), Point::new(1., 1., 1.))
+    }
+
+    // Apply the transform to a point.
+    pub fn apply(&self, point: &Point) -> Point {
+        let scaled = Point::new(
+            point.x * self.scale.x, 
+            point.y * self.scale.y, 
+            point.z * self.scale.z
+        );
+        let rotated = self.rotation.rotate(&scaled);
+        Point::new(
+            rotated.x + self.translation.x,
+            rotated.y + self.translation.y,
+            rotated.z + self.translation.z
+        )
+    }
+
+    // Undo the transform on a point.
+    // Components with zero scale are collapsed, and map back to zero.
+    pub fn apply_inverse(&self, point: &Point) -> Point {
+        let unrotated = self.rotation.conjugate().rotate(&Point::new(
+            point.x - self.translation.x,
+            point.y - self.translation.y,
+            point.z - self.translation.z
+        ));
+        let unscale = |value: f32, scale: f32| if scale == 0. { 0. } else { value / scale };
+        Point::new(
+            unscale(unrotated.x, self.scale.x),
+            unscale(unrotated.y, self.scale.y),
+            unscale(unrotated.z, self.scale.z)
+        )
+    }
+}
+
 pub struct Camera {
     // Location of the camera
     pub coordinates: Point,
@@ -32,11 +218,17 @@ pub struct Camera {
     // In radians
     pub viewport_fov: f32,
 
+    // Width of the view in world units when orthographic, or None for perspective.
+    pub orthographic_width: Option<f32>,
+
     // Screen to render.
     pub screen: screen::Screen,
 
     // Whether braille mode is active
-    pub braille_mode: bool
+    pub braille_mode: bool,
+
+    // Number of threads to rasterize edges with.
+    pub threads: usize
 }
 
 #[allow(dead_code)]
@@ -51,8 +243,10 @@ impl Camera {
             coordinates, 
             yaw, pitch, roll, 
             viewport_distance, viewport_fov, 
+            orthographic_width: None,
             screen: screen::Screen::new(),
-            braille_mode: true
+            braille_mode: true,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get())
         }
     }
 
@@ -85,37 +279,202 @@ impl Camera {
         Point::new(unrolled_x, unrolled_y, unrolled_z)
     }
 
+    // Convert camera to world coordinates, undoing world_to_camera.
+    fn camera_to_world(&self, point: &Point) -> Point {
+        let (s_yaw, s_pitch, s_roll) = (self.yaw.sin(), self.pitch.sin(), self.roll.sin());
+        let (c_yaw, c_pitch, c_roll) = (self.yaw.cos(), self.pitch.cos(), self.roll.cos());
+
+        // Redo roll.
+        let rolled_x = point.x * c_roll + point.y * s_roll;
+        let rolled_y = -point.x * s_roll + point.y * c_roll;
+        let rolled_z = point.z;
+
+        // Redo pitch.
+        let pitched_x = rolled_x;
+        let pitched_y = rolled_y * c_pitch + rolled_z * s_pitch;
+        let pitched_z = -rolled_y * s_pitch + rolled_z * c_pitch;
+
+        // Redo yaw, and move back to the camera's position.
+        Point::new(
+            pitched_x * c_yaw + pitched_z * s_yaw + self.coordinates.x,
+            pitched_y + self.coordinates.y,
+            -pitched_x * s_yaw + pitched_z * c_yaw + self.coordinates.z
+        )
+    }
+
     // Convert camera to screen coordinates.
     fn camera_to_screen(&self, point: &Point) -> screen::Point {
-        // Project onto viewport coordinates.
-        let viewport_x = point.x * self.viewport_distance / point.z;
-        let viewport_y = point.y * self.viewport_distance / point.z;
+        let (screen_x, screen_y) = self.camera_to_subpixel(point);
+
+        // Round.
+        screen::Point::new(screen_x.round() as i32, screen_y.round() as i32)
+    }
+
+    // Convert camera to unrounded screen coordinates.
+    fn camera_to_subpixel(&self, point: &Point) -> (f32, f32) {
+        // Project onto viewport coordinates, and compute the viewport width,
+        // based on the fov in perspective, or the fixed width when orthographic.
+        let (viewport_x, viewport_y, viewport_width) = match self.orthographic_width {
+            Some(width) => (point.x, point.y, width),
+            None => (
+                point.x * self.viewport_distance / point.z,
+                point.y * self.viewport_distance / point.z,
+                2. * self.viewport_distance * (self.viewport_fov / 2.).tan()
+            )
+        };
+
+        // Compute viewport height based on screen width and height.
+        let viewport_height = self.aspect_ratio() * viewport_width;
+
+        // Project to screen coordinates.
+        let screen_x = (viewport_x / viewport_width + 0.5) * self.screen.width as f32;
+        let screen_y = (1.0 - (viewport_y / viewport_height + 0.5)) * self.screen.height as f32;
 
-        // Compute viewport width and height based on screen width, height, and fov.
-        let viewport_width = 2. * self.viewport_distance * (self.viewport_fov / 2.).tan();
+        (screen_x, screen_y)
+    }
+
+    // Ray in world space through unrounded screen coordinates, as an origin and a unit direction.
+    // In perspective it starts at the camera, and when orthographic on the camera's plane.
+    pub fn ray(&self, screen_x: f32, screen_y: f32) -> (Point, Point) {
+        let viewport_width = match self.orthographic_width {
+            Some(width) => width,
+            None => 2. * self.viewport_distance * (self.viewport_fov / 2.).tan()
+        };
+        let viewport_height = self.aspect_ratio() * viewport_width;
+
+        // Undo camera_to_subpixel.
+        let viewport_x = (screen_x / self.screen.width as f32 - 0.5) * viewport_width;
+        let viewport_y = (0.5 - screen_y / self.screen.height as f32) * viewport_height;
+
+        let (origin, through) = match self.orthographic_width {
+            Some(_) => (Point::new(viewport_x, viewport_y, 0.), Point::new(viewport_x, viewport_y, 1.)),
+            None => (Point::new(0., 0., 0.), Point::new(viewport_x, viewport_y, self.viewport_distance))
+        };
+        let (origin, through) = (self.camera_to_world(&origin), self.camera_to_world(&through));
+
+        let direction = Point::new(through.x - origin.x, through.y - origin.y, through.z - origin.z);
+        let length = (direction.x.powi(2) + direction.y.powi(2) + direction.z.powi(2)).sqrt();
+        (origin, Point::new(direction.x / length, direction.y / length, direction.z / length))
+    }
 
+    // Width in world units of a subpixel, at the given depth in front of the camera.
+    pub fn subpixel_width(&self, depth: f32) -> f32 {
+        let viewport_width = match self.orthographic_width {
+            Some(width) => width,
+            None => 2. * depth * (self.viewport_fov / 2.).tan()
+        };
+        viewport_width / self.screen.width.max(1) as f32
+    }
+
+    // Ratio of the viewport's height to its width.
+    fn aspect_ratio(&self) -> f32 {
         // Adjust aspect ratio for braille mode
         // In braille mode, characters are twice as tall as they are wide
-        let aspect_ratio = if self.braille_mode {
+        if self.braille_mode {
             // For braille (2x4 grid), divide height by 2 to compensate for the taller characters
             self.screen.height as f32 / self.screen.width as f32
         } else {
             // For block mode (2x2 grid), use normal aspect ratio
             (self.screen.height as f32 * 2.0) / self.screen.width as f32
+        }
+    }
+
+    // Whether any of a box in model space, given as (min, max), may be inside the view frustum.
+    // A box is outside if all its corners are outside the same plane of the frustum.
+    fn box_visible(&self, model: &model::Model, min: &Point, max: &Point) -> bool {
+        // Half the view's width grows with depth in perspective, and is fixed when orthographic.
+        let (tan_x, half_width) = match self.orthographic_width {
+            Some(width) => (0., width / 2.),
+            None => ((self.viewport_fov / 2.).tan(), 0.)
         };
+        let (tan_y, half_height) = (tan_x * self.aspect_ratio(), half_width * self.aspect_ratio());
 
-        let viewport_height = aspect_ratio * viewport_width;
+        let corners = [
+            Point::new(min.x, min.y, min.z), Point::new(max.x, min.y, min.z),
+            Point::new(min.x, max.y, min.z), Point::new(max.x, max.y, min.z),
+            Point::new(min.x, min.y, max.z), Point::new(max.x, min.y, max.z),
+            Point::new(min.x, max.y, max.z), Point::new(max.x, max.y, max.z)
+        ].map(|corner| self.world_to_camera(&model.model_to_world(&corner)));
 
-        // Project to screen coordinates.
-        let screen_x = (viewport_x / viewport_width + 0.5) * self.screen.width as f32;
-        let screen_y = (1.0 - (viewport_y / viewport_height + 0.5)) * self.screen.height as f32;
+        let planes: [&dyn Fn(&Point) -> bool; 5] = [
+            &|point| point.z < self.viewport_distance,
+            &|point| point.x > point.z * tan_x + half_width,
+            &|point| -point.x > point.z * tan_x + half_width,
+            &|point| point.y > point.z * tan_y + half_height,
+            &|point| -point.y > point.z * tan_y + half_height
+        ];
+        !planes.iter().any(|outside| corners.iter().all(outside))
+    }
 
-        // Round.
-        screen::Point::new(screen_x.round() as i32, screen_y.round() as i32)
+    // Edges of a model that may be visible, skipping boxes of its hierarchy outside the view frustum.
+    fn visible_edges<'a>(&self, model: &'a model::Model) -> borrow::Cow<'a, [(Point, Point)]> {
+        if model.bvh.is_empty() { return borrow::Cow::Borrowed(&model.edges) }
+
+        let ranges = model.bvh.visible(|min, max| self.box_visible(model, min, max));
+        match ranges.as_slice() {
+            [range] => borrow::Cow::Borrowed(&model.edges[range.clone()]),
+            _ => borrow::Cow::Owned(
+                ranges.into_iter().flat_map(|range| model.edges[range].iter().copied()).collect()
+            )
+        }
+    }
+
+    // Bounds in world space of the points and edge ends of a model that are on screen, if any are.
+    pub fn visible_bounds(&self, model: &model::Model) -> Option<(Point, Point)> {
+        let edges = self.visible_edges(model);
+        let mut on_screen = edges.iter()
+            .flat_map(|edge| [edge.0, edge.1])
+            .chain(model.points.iter().copied())
+            .map(|point| model.model_to_world(&point))
+            .filter(|point| {
+                let camera_point = self.world_to_camera(point);
+                if camera_point.z < self.viewport_distance { return false }
+
+                let (screen_x, screen_y) = self.camera_to_subpixel(&camera_point);
+                (0. ..=self.screen.width as f32).contains(&screen_x) 
+                    && (0. ..=self.screen.height as f32).contains(&screen_y)
+            });
+
+        let first = on_screen.next()?;
+        Some(on_screen.fold((first, first), |(min, max), point| (
+            Point::new(min.x.min(point.x), min.y.min(point.y), min.z.min(point.z)),
+            Point::new(max.x.max(point.x), max.y.max(point.y), max.z.max(point.z))
+        )))
+    }
+
+    // Point of a model nearest to unrounded screen coordinates once projected, as its distance there and its world position.
+    // Only points within the radius, in subpixels, count, and of those about as near, the one nearest the camera wins.
+    pub fn nearest_point(&self, model: &model::Model, screen_x: f32, screen_y: f32, radius: f32) -> Option<(f32, Point)> {
+        model.points.iter()
+            .map(|point| model.model_to_world(point))
+            .filter_map(|point| {
+                let camera_point = self.world_to_camera(&point);
+                if camera_point.z < self.viewport_distance { return None }
+
+                let (x, y) = self.camera_to_subpixel(&camera_point);
+                let distance = (x - screen_x).hypot(y - screen_y);
+                (distance <= radius).then_some((distance, camera_point.z, point))
+            })
+            .min_by(|a, b| a.0.round().total_cmp(&b.0.round()).then(a.1.total_cmp(&b.1)))
+            .map(|(distance, _, point)| (distance, point))
+    }
+
+    // Plot a 3d point as a small square, reaching size subpixels out from it, so that it stands out.
+    pub fn marker(&mut self, point: &Point, size: i32) {
+        let camera_point = self.world_to_camera(point);
+        if camera_point.z < self.viewport_distance { return }
+
+        let center = self.camera_to_screen(&camera_point);
+        let corners = [(-size, -size), (size, -size), (size, size), (-size, size)]
+            .map(|(x, y)| screen::Point::new(center.x + x, center.y + y));
+        for (start, end) in corners.iter().zip(corners.iter().cycle().skip(1)) {
+            self.screen.line(start, end);
+        }
     }
 
     // Plot points of a given model.
     pub fn plot_model_points(&mut self, model: &model::Model) {
+        self.screen.set_color(model.color);
         for point in model.points.iter() {
             self.write(true, &model.model_to_world(point));
         }
@@ -123,11 +482,32 @@ impl Camera {
 
     // Plot edges of a given model.
     pub fn plot_model_edges(&mut self, model: &model::Model) {
-        for edge in model.edges.iter() {
-            self.edge( 
-                &model.model_to_world(&edge.0),
-                &model.model_to_world(&edge.1)
-            );
+        self.screen.set_color(model.color);
+        let edges = self.visible_edges(model);
+        self.edges(model, &edges);
+    }
+
+    // Plot a simplified version of a model with at most about max_edges edges, for use while interacting.
+    // Falls back to a sample of the coarsest level of detail if even that is too large.
+    pub fn plot_model_edges_coarse(&mut self, model: &model::Model, max_edges: usize) {
+        self.screen.set_color(model.color);
+        let (_, edges) = model.lod(max_edges);
+        let step = edges.len().div_ceil(max_edges.max(1)).max(1);
+        if step == 1 { 
+            self.edges(model, edges);
+        } else {
+            let edges: Vec<(Point, Point)> = edges.iter().step_by(step).copied().collect();
+            self.edges(model, &edges);
+        }
+    }
+
+    // Plot the points of a simplified version of a model, matching plot_model_edges_coarse.
+    pub fn plot_model_points_coarse(&mut self, model: &model::Model, max_edges: usize) {
+        self.screen.set_color(model.color);
+        let (points, _) = model.lod(max_edges);
+        let step = points.len().div_ceil(max_edges.max(1)).max(1);
+        for point in points.iter().step_by(step) {
+            self.write(true, &model.model_to_world(point));
         }
     }
 
@@ -141,6 +521,15 @@ impl Camera {
 
     // Plot a 3d edge.
     pub fn edge(&mut self, start: &Point, end: &Point) {
+        if let Some((start, end)) = self.project_edge(start, end) {
+            self.screen.line(&start, &end);
+        }
+    }
+
+    // Project a 3d edge to the screen, clipping it to what is in front of the viewport,
+    // and then to the edges of the screen, so that nothing off screen is rasterized.
+    // Returns None if none of the edge is visible.
+    fn project_edge(&self, start: &Point, end: &Point) -> Option<(screen::Point, screen::Point)> {
 
         // Compute points in camera space, and find if we need to clip.
         let camera_start = self.world_to_camera(start);
@@ -149,15 +538,11 @@ impl Camera {
         let clip_end = camera_end.z < self.viewport_distance;
 
         // If we need to clip both points, don't plot.
-        if clip_start && clip_end { return }
+        if clip_start && clip_end { return None }
 
         // If we don't need to clip either point, plot a line.
         if !clip_start && !clip_end {
-            self.screen.line(
-                &self.camera_to_screen(&camera_start), 
-                &self.camera_to_screen(&camera_end)
-            );
-            return
+            return self.clip_to_screen(&camera_start, &camera_end)
         }
 
         // Otherwise identify the clipped and unclipped point.
@@ -179,9 +564,52 @@ impl Camera {
         );
 
         // Plot.
-        self.screen.line(
-            &self.camera_to_screen(&new_clipped), 
-            &self.camera_to_screen(&unclipped)
-        )    
+        self.clip_to_screen(&new_clipped, &unclipped)
+    }
+
+    // Project an edge in front of the viewport, and clip it to the screen.
+    fn clip_to_screen(&self, start: &Point, end: &Point) -> Option<(screen::Point, screen::Point)> {
+        let (start, end) = self.screen.clip_line(
+            self.camera_to_subpixel(start), 
+            self.camera_to_subpixel(end)
+        )?;
+
+        Some((
+            screen::Point::new(start.0.round() as i32, start.1.round() as i32),
+            screen::Point::new(end.0.round() as i32, end.1.round() as i32)
+        ))
+    }
+
+    // Plot many 3d edges, splitting them across threads when there are enough to be worth it.
+    // Every thread rasterizes into its own layer, and the layers are merged in order.
+    fn edges(&mut self, model: &model::Model, edges: &[(Point, Point)]) {
+        if self.threads <= 1 || edges.len() < PARALLEL_MIN_EDGES {
+            for edge in edges.iter() {
+                self.edge(&model.model_to_world(&edge.0), &model.model_to_world(&edge.1));
+            }
+            return
+        }
+
+        let camera = &*self;
+        let chunk_size = edges.len().div_ceil(self.threads);
+        let layers: Vec<screen::Screen> = thread::scope(|scope| {
+            let handles: Vec<_> = edges.chunks(chunk_size).map(|chunk| scope.spawn(move || {
+                let mut layer = camera.screen.new_layer();
+                for edge in chunk.iter() {
+                    let start = model.model_to_world(&edge.0);
+                    let end = model.model_to_world(&edge.1);
+                    if let Some((start, end)) = camera.project_edge(&start, &end) {
+                        layer.line(&start, &end);
+                    }
+                }
+                layer
+            })).collect();
+
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        for layer in layers.iter() {
+            self.screen.merge(layer);
+        }
     }
 }
@@ -0,0 +1,293 @@
+use crate::three;
+use std::*;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+use time::{Duration, Instant};
+
+// Where the camera orbits around, and from which angles and distance.
+#[derive(Copy, Clone)]
+pub struct View {
+    pub center: three::Point,
+
+    // In radians, yaw turns around the center from z- towards x+, pitch rises towards y+,
+    // and roll turns the camera around the direction it looks in.
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+
+    pub distance: f32
+}
+
+impl View {
+    // Create a new view, without roll.
+    pub fn new(center: three::Point, yaw: f32, pitch: f32, distance: f32) -> View {
+        View { center, yaw, pitch, roll: 0., distance }
+    }
+
+    // Blend from this view to another, as t goes from 0 to 1, turning the short way around.
+    pub fn lerp(&self, other: &View, t: f32) -> View {
+        let mix = |from: f32, to: f32| from + (to - from) * t;
+        let turn = |from: f32, to: f32| from + ((to - from + PI).rem_euclid(2. * PI) - PI) * t;
+
+        View {
+            center: three::Point::new(
+                mix(self.center.x, other.center.x),
+                mix(self.center.y, other.center.y),
+                mix(self.center.z, other.center.z)
+            ),
+            yaw: turn(self.yaw, other.yaw),
+            pitch: mix(self.pitch, other.pitch),
+            roll: turn(self.roll, other.roll),
+            distance: mix(self.distance, other.distance)
+        }
+    }
+
+    // Orientation of the camera, turning camera space into world space.
+    pub fn orientation(&self) -> three::Quaternion {
+        let yaw = three::Quaternion::from_axis_angle(&three::Axis::Y.unit(), -self.yaw);
+        let pitch = three::Quaternion::from_axis_angle(&three::Axis::X.unit(), self.pitch);
+        let roll = three::Quaternion::from_axis_angle(&three::Axis::Z.unit(), -self.roll);
+        yaw.mul(&pitch).mul(&roll)
+    }
+
+    // Set the angles to match an orientation of the camera.
+    // See https://www.geometrictools.com/Documentation/EulerAngles.pdf, for a rotation about y, then x, then z.
+    pub fn set_orientation(&mut self, orientation: &three::Quaternion) {
+        let three::Quaternion { w, x, y, z } = orientation.normalize();
+        let (m00, m02) = (1. - 2. * (y * y + z * z), 2. * (x * z + w * y));
+        let (m10, m11, m12) = (2. * (x * y + w * z), 1. - 2. * (x * x + z * z), 2. * (y * z - w * x));
+        let (m20, m22) = (2. * (x * z - w * y), 1. - 2. * (x * x + y * y));
+
+        self.pitch = (-m12).clamp(-1., 1.).asin();
+
+        // Looking straight up or down, yaw and roll turn around the same axis, so put it all in yaw.
+        if m12.abs() < 1. - 1e-6 {
+            self.yaw = -m02.atan2(m22);
+            self.roll = -m10.atan2(m11);
+        } else {
+            self.yaw = -(-m20).atan2(m00);
+            self.roll = 0.;
+        }
+    }
+
+    // Position of the camera, at the distance from the center, looking at it.
+    pub fn eye(&self) -> three::Point {
+        let forward = self.orientation().rotate(&three::Axis::Z.unit());
+        three::Point::new(
+            self.center.x - forward.x * self.distance,
+            self.center.y - forward.y * self.distance,
+            self.center.z - forward.z * self.distance
+        )
+    }
+
+    // Move the center so that the camera is at the given position, keeping the angles and distance.
+    pub fn set_eye(&mut self, eye: &three::Point) {
+        let forward = self.orientation().rotate(&three::Axis::Z.unit());
+        self.center = three::Point::new(
+            eye.x + forward.x * self.distance,
+            eye.y + forward.y * self.distance,
+            eye.z + forward.z * self.distance
+        );
+    }
+
+    // Move the center and camera together, by distances along the camera's axes.
+    pub fn translate(&mut self, right: f32, up: f32, forward: f32) {
+        let movement = self.orientation().rotate(&three::Point::new(right, up, forward));
+        self.center = three::Point::new(
+            self.center.x + movement.x,
+            self.center.y + movement.y,
+            self.center.z + movement.z
+        );
+    }
+
+    // Scale the center and distance around a point, moving the camera towards it when the scale is below one,
+    // so that the point stays in the same place on screen.
+    pub fn scale_around(&mut self, point: &three::Point, scale: f32) {
+        self.center = three::Point::new(
+            point.x + (self.center.x - point.x) * scale,
+            point.y + (self.center.y - point.y) * scale,
+            point.z + (self.center.z - point.z) * scale
+        );
+        self.distance *= scale;
+    }
+
+    // Turn the camera around the center by a rotation in camera space.
+    pub fn rotate(&mut self, rotation: &three::Quaternion) {
+        self.set_orientation(&self.orientation().mul(rotation));
+    }
+
+    // The same view, from the other side of the center.
+    pub fn opposite(&self) -> View {
+        View { yaw: self.yaw + PI, pitch: -self.pitch, ..*self }
+    }
+}
+
+// Standard views, as CAD packages offer them.
+#[derive(Copy, Clone)]
+pub enum Preset {
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+
+    // From the front, right and top at once, with all three axes equally foreshortened.
+    Isometric
+}
+
+impl Preset {
+    // View looking at the center from the preset's side, at the given distance.
+    pub fn view(&self, center: three::Point, distance: f32) -> View {
+        let (yaw, pitch) = match self {
+            Preset::Front => (PI, 0.),
+            Preset::Back => (0., 0.),
+            Preset::Left => (-FRAC_PI_2, 0.),
+            Preset::Right => (FRAC_PI_2, 0.),
+            Preset::Top => (PI, FRAC_PI_2),
+            Preset::Bottom => (PI, -FRAC_PI_2),
+            Preset::Isometric => (3. * FRAC_PI_4, (1. / 2f32.sqrt()).atan())
+        };
+        View::new(center, yaw, pitch, distance)
+    }
+}
+
+// Eased movement from one view to another, over a fixed duration.
+pub struct Transition {
+    from: View,
+    to: View,
+    start: Instant,
+    duration: Duration
+}
+
+impl Transition {
+    // Start moving between the views now.
+    pub fn new(from: View, to: View, duration: Duration) -> Transition {
+        Transition { from, to, start: Instant::now(), duration }
+    }
+
+    // View the transition ends at.
+    pub fn target(&self) -> View {
+        self.to
+    }
+
+    // Where the transition is at this moment, and whether it has finished.
+    pub fn view(&self) -> (View, bool) {
+        let t = (self.start.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.);
+
+        // Smoothstep, to start and stop gently.
+        let eased = t * t * (3. - 2. * t);
+        (self.from.lerp(&self.to, eased), t >= 1.)
+    }
+}
+
+// Time for the speed of inertia to fall by about two thirds.
+const INERTIA_TIME_CONSTANT: f32 = 0.4;
+
+// Slowest turn worth carrying on with, in radians per second.
+const INERTIA_MIN_SPEED: f32 = 0.05;
+
+// Weight of the newest measurement in the velocity average.
+const INERTIA_SMOOTHING: f32 = 0.5;
+
+// Drags released later than this after the mouse last moved have stopped, and don't carry on.
+const INERTIA_RELEASE_WINDOW: Duration = Duration::from_millis(80);
+
+// Turning that carries on after a drag is released, slowing down over time.
+pub struct Inertia {
+    // Turn per second, as (yaw, pitch, 0) for a turntable, or a trackball's axis scaled by its angle.
+    velocity: three::Point,
+
+    last_update: Instant
+}
+
+impl Inertia {
+    // Create a new inertia, at rest.
+    pub fn new() -> Inertia {
+        Inertia { velocity: three::Point::new(0., 0., 0.), last_update: Instant::now() }
+    }
+
+    // Record a turn made by dragging, to follow the speed of the mouse.
+    pub fn record(&mut self, turn: &three::Point) {
+        let elapsed = self.last_update.elapsed().as_secs_f32().max(0.001);
+        let v = &mut self.velocity;
+        v.x += INERTIA_SMOOTHING * (turn.x / elapsed - v.x);
+        v.y += INERTIA_SMOOTHING * (turn.y / elapsed - v.y);
+        v.z += INERTIA_SMOOTHING * (turn.z / elapsed - v.z);
+        self.last_update = Instant::now();
+    }
+
+    // Let go, returning whether the drag was still moving and carries on.
+    pub fn release(&mut self) -> bool {
+        if self.last_update.elapsed() > INERTIA_RELEASE_WINDOW { self.stop() }
+        self.speed() >= INERTIA_MIN_SPEED
+    }
+
+    // Stop turning.
+    pub fn stop(&mut self) {
+        self.velocity = three::Point::new(0., 0., 0.);
+        self.last_update = Instant::now();
+    }
+
+    // Turn to make since the last step, in the same form as the velocity, or None once too slow.
+    pub fn step(&mut self) -> Option<three::Point> {
+        let elapsed = self.last_update.elapsed().as_secs_f32();
+        self.last_update = Instant::now();
+
+        let decay = (-elapsed / INERTIA_TIME_CONSTANT).exp();
+        let v = &mut self.velocity;
+        (v.x, v.y, v.z) = (v.x * decay, v.y * decay, v.z * decay);
+        if self.speed() < INERTIA_MIN_SPEED { return None }
+
+        let v = &self.velocity;
+        Some(three::Point::new(v.x * elapsed, v.y * elapsed, v.z * elapsed))
+    }
+
+    // Speed in radians per second.
+    fn speed(&self) -> f32 {
+        (self.velocity.x.powi(2) + self.velocity.y.powi(2) + self.velocity.z.powi(2)).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether two orientations turn points the same way.
+    fn same_orientation(a: &three::Quaternion, b: &three::Quaternion) -> bool {
+        [three::Axis::X, three::Axis::Y, three::Axis::Z].iter().all(|axis| {
+            let (a, b) = (a.rotate(&axis.unit()), b.rotate(&axis.unit()));
+            (a.x - b.x).abs() < 1e-4 && (a.y - b.y).abs() < 1e-4 && (a.z - b.z).abs() < 1e-4
+        })
+    }
+
+    #[test]
+    fn set_orientation_round_trips() {
+        for (yaw, pitch, roll) in [(0., 0., 0.), (0.5, 0.3, 0.2), (-2., -1.2, 3.), (3., 1.5, -0.7), (1., 0., -2.5)] {
+            let view = View { roll, ..View::new(three::Point::new(0., 0., 0.), yaw, pitch, 1.) };
+            let mut copy = View::new(three::Point::new(0., 0., 0.), 0., 0., 1.);
+            copy.set_orientation(&view.orientation());
+
+            assert!(same_orientation(&view.orientation(), &copy.orientation()));
+            assert!((copy.yaw - yaw).abs() < 1e-4 && (copy.pitch - pitch).abs() < 1e-4 && (copy.roll - roll).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn set_orientation_looking_straight_down() {
+        // Yaw and roll turn around the same axis, so only the orientation itself comes back.
+        let view = View { roll: 0.4, ..View::new(three::Point::new(0., 0., 0.), 0.3, FRAC_PI_2, 1.) };
+        let mut copy = View::new(three::Point::new(0., 0., 0.), 0., 0., 1.);
+        copy.set_orientation(&view.orientation());
+
+        assert!(same_orientation(&view.orientation(), &copy.orientation()));
+        assert_eq!(copy.roll, 0.);
+    }
+
+    #[test]
+    fn translate_follows_roll() {
+        // Rolled a quarter turn, the right of the screen points down.
+        let mut view = View { roll: FRAC_PI_2, ..View::new(three::Point::new(0., 0., 0.), 0., 0., 1.) };
+        view.translate(1., 0., 0.);
+        assert!(view.center.x.abs() < 1e-5 && view.center.y.abs() > 0.99 && view.center.z.abs() < 1e-5);
+    }
+}